@@ -8,14 +8,26 @@ pub enum Inline<'a> {
     Link {
         children: Vec<Inline<'a>>,
         url: Cow<'a, str>,
+        title: Option<Cow<'a, str>>,
     },
     Image {
         alt: Cow<'a, str>,
         url: Cow<'a, str>,
+        title: Option<Cow<'a, str>>,
     },
     CodeSpan(Cow<'a, str>),
     Strikethrough(Vec<Inline<'a>>),
     RawHtml(Cow<'a, str>),
+    // 段落内的普通换行
+    SoftBreak,
+    // 行尾两个以上空格或反斜杠
+    HardBreak,
+    // [^label]：number 为按首次引用排列的编号，index 为同一脚注的第几次引用，从 1 开始
+    FootnoteReference {
+        label: Cow<'a, str>,
+        number: usize,
+        index: usize,
+    },
 }
 
 impl<'a> Inline<'a> {
@@ -30,15 +42,32 @@ impl<'a> Inline<'a> {
                 Inline::Strikethrough(v.into_iter().map(|i| i.into_owned()).collect())
             }
             Inline::CodeSpan(c) => Inline::CodeSpan(Cow::Owned(c.into_owned())),
-            Inline::Link { children, url } => Inline::Link {
+            Inline::Link {
+                children,
+                url,
+                title,
+            } => Inline::Link {
                 children: children.into_iter().map(|i| i.into_owned()).collect(),
                 url: Cow::Owned(url.into_owned()),
+                title: title.map(|t| Cow::Owned(t.into_owned())),
             },
-            Inline::Image { alt, url } => Inline::Image {
+            Inline::Image { alt, url, title } => Inline::Image {
                 alt: Cow::Owned(alt.into_owned()),
                 url: Cow::Owned(url.into_owned()),
+                title: title.map(|t| Cow::Owned(t.into_owned())),
             },
             Inline::RawHtml(c) => Inline::RawHtml(Cow::Owned(c.into_owned())),
+            Inline::SoftBreak => Inline::SoftBreak,
+            Inline::HardBreak => Inline::HardBreak,
+            Inline::FootnoteReference {
+                label,
+                number,
+                index,
+            } => Inline::FootnoteReference {
+                label: Cow::Owned(label.into_owned()),
+                number,
+                index,
+            },
         }
     }
 }
@@ -56,10 +85,17 @@ pub enum Block<'a> {
     CodeBlock {
         code: Cow<'a, str>,
         language: Cow<'a, str>,
+        info: CodeInfo<'a>,
     },
     ThematicBreak,
+    // 原样输出的 HTML 块
+    HtmlBlock(Cow<'a, str>),
     List {
         ordered: bool,
+        // 有序列表的起始编号，无序列表为 1
+        start: u32,
+        // 紧凑列表的段落不包 <p>
+        tight: bool,
         items: Vec<ListItem<'a>>,
     },
     Table {
@@ -67,6 +103,13 @@ pub enum Block<'a> {
         rows: Vec<Vec<Vec<Inline<'a>>>>,
         alignments: Vec<Alignment>,
     },
+    // 脚注定义，解析结束后按编号移到文档末尾；references 为被引用的次数
+    FootnoteDefinition {
+        label: Cow<'a, str>,
+        number: usize,
+        references: usize,
+        children: Vec<Block<'a>>,
+    },
 }
 
 impl<'a> Block<'a> {
@@ -79,16 +122,29 @@ impl<'a> Block<'a> {
             Block::Paragraph { children } => Block::Paragraph {
                 children: children.into_iter().map(|i| i.into_owned()).collect(),
             },
-            Block::CodeBlock { code, language } => Block::CodeBlock {
+            Block::CodeBlock {
+                code,
+                language,
+                info,
+            } => Block::CodeBlock {
                 code: Cow::Owned(code.into_owned()),
                 language: Cow::Owned(language.into_owned()),
+                info: info.into_owned(),
             },
             Block::BlockQuote(children) => {
                 Block::BlockQuote(children.into_iter().map(|b| b.into_owned()).collect())
             }
             Block::ThematicBreak => Block::ThematicBreak,
-            Block::List { ordered, items } => Block::List {
+            Block::HtmlBlock(html) => Block::HtmlBlock(Cow::Owned(html.into_owned())),
+            Block::List {
+                ordered,
+                start,
+                tight,
+                items,
+            } => Block::List {
                 ordered,
+                start,
+                tight,
                 items: items.into_iter().map(|li| li.into_owned()).collect(),
             },
             Block::Table {
@@ -110,19 +166,73 @@ impl<'a> Block<'a> {
                     .collect(),
                 alignments,
             },
+            Block::FootnoteDefinition {
+                label,
+                number,
+                references,
+                children,
+            } => Block::FootnoteDefinition {
+                label: Cow::Owned(label.into_owned()),
+                number,
+                references,
+                children: children.into_iter().map(|b| b.into_owned()).collect(),
+            },
         }
     }
 }
 
+// 代码块 info string 中语言之外的部分
+#[derive(Debug, Clone, Default)]
+pub struct CodeInfo<'a> {
+    // key=value 与 #id
+    pub attributes: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    // .class 与其他单词
+    pub classes: Vec<Cow<'a, str>>,
+    // {1,3-5}
+    pub highlight_lines: Vec<LineRange>,
+}
+
+impl<'a> CodeInfo<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.classes.is_empty() && self.highlight_lines.is_empty()
+    }
+
+    pub fn into_owned<'b>(self) -> CodeInfo<'b> {
+        CodeInfo {
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(|(k, v)| (Cow::Owned(k.into_owned()), Cow::Owned(v.into_owned())))
+                .collect(),
+            classes: self
+                .classes
+                .into_iter()
+                .map(|c| Cow::Owned(c.into_owned()))
+                .collect(),
+            highlight_lines: self.highlight_lines,
+        }
+    }
+}
+
+// 闭区间，行号从 1 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct ListItem<'a> {
     pub children: Vec<Block<'a>>,
+    // 任务列表项的勾选状态，普通列表项为 None
+    pub checked: Option<bool>,
 }
 
 impl<'a> ListItem<'a> {
     pub fn into_owned<'b>(self) -> ListItem<'b> {
         ListItem {
             children: self.children.into_iter().map(|b| b.into_owned()).collect(),
+            checked: self.checked,
         }
     }
 }
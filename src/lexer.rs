@@ -1,7 +1,25 @@
-pub mod inline_parser;
-pub mod list_parser;
-pub mod parser;
+mod autolink;
+mod delimiter;
+mod entities;
+mod escape;
+mod footnotes;
+mod html_scanner;
+mod info_parser;
+mod inline_parser;
+mod link_parser;
+mod list_parser;
+mod options;
+mod parser;
 
-pub use inline_parser::*;
-pub use list_parser::*;
+pub(crate) use autolink::*;
+pub(crate) use delimiter::*;
+pub(crate) use entities::*;
+pub(crate) use escape::*;
+pub(crate) use footnotes::*;
+pub(crate) use html_scanner::*;
+pub(crate) use info_parser::*;
+pub(crate) use inline_parser::*;
+pub(crate) use link_parser::*;
+pub(crate) use list_parser::*;
+pub use options::*;
 pub use parser::*;
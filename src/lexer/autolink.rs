@@ -0,0 +1,234 @@
+use std::borrow::Cow;
+
+use crate::ast::Inline;
+
+// <scheme:...> 或 <user@example.com>，input 从 < 开始
+// 返回 (消耗的字节数, 链接地址, 显示文本)
+pub fn parse_autolink(input: &str) -> Option<(usize, Cow<'_, str>, &str)> {
+    let rest = input.strip_prefix('<')?;
+    // 自动链接里不能有空白、控制字符和 <，遇到它们就可以停止查找
+    let end = rest
+        .bytes()
+        .position(|b| b == b'>' || b == b'<' || b <= b' ')?;
+    if rest.as_bytes()[end] != b'>' {
+        return None;
+    }
+    let content = &rest[..end];
+
+    if is_uri(content) {
+        Some((end + 2, Cow::Borrowed(content), content))
+    } else if is_email(content) {
+        Some((end + 2, Cow::Owned(format!("mailto:{content}")), content))
+    } else {
+        None
+    }
+}
+
+// 协议名 2~32 个字符，之后不能有空白、控制字符和 < >
+fn is_uri(content: &str) -> bool {
+    let Some((scheme, rest)) = content.split_once(':') else {
+        return false;
+    };
+
+    let valid_scheme = (2..=32).contains(&scheme.len())
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'.' || b == b'-');
+
+    valid_scheme
+        && rest
+            .bytes()
+            .all(|b| b > b' ' && b != b'<' && b != b'>' && b != 0x7f)
+}
+
+fn is_email(content: &str) -> bool {
+    let Some((local, domain)) = content.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && local
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b".!#$%&'*+/=?^_`{|}~-".contains(&b))
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+// GFM 扩展自动链接：把文本中的 www.、http(s):// 网址和邮箱地址转换成链接
+// 不进入已有链接内部
+pub fn link_extended(inlines: Vec<Inline<'_>>) -> Vec<Inline<'_>> {
+    let mut output = Vec::with_capacity(inlines.len());
+
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => link_text(text, &mut output),
+            Inline::Emphasis(children) => output.push(Inline::Emphasis(link_extended(children))),
+            Inline::Strong(children) => output.push(Inline::Strong(link_extended(children))),
+            Inline::Strikethrough(children) => {
+                output.push(Inline::Strikethrough(link_extended(children)))
+            }
+            other => output.push(other),
+        }
+    }
+
+    output
+}
+
+fn link_text<'a>(text: Cow<'a, str>, output: &mut Vec<Inline<'a>>) {
+    let bytes = text.as_bytes();
+    // 尚未输出的文本起点
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let found = if bytes[i] == b'@' {
+            scan_email(&text, last, i)
+        } else if i == 0 || is_url_boundary(bytes[i - 1]) {
+            scan_url(&text[i..]).map(|(len, url)| (i, i + len, url))
+        } else {
+            None
+        };
+
+        match found {
+            Some((start, end, url)) => {
+                if start > last {
+                    output.push(Inline::Text(Cow::Owned(text[last..start].to_string())));
+                }
+                output.push(Inline::Link {
+                    children: vec![Inline::Text(Cow::Owned(text[start..end].to_string()))],
+                    url: Cow::Owned(url),
+                    title: None,
+                });
+                last = end;
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+
+    if last == 0 {
+        output.push(Inline::Text(text));
+    } else if last < text.len() {
+        output.push(Inline::Text(Cow::Owned(text[last..].to_string())));
+    }
+}
+
+// 网址只能出现在行首、空白或 * _ ~ ( 之后
+fn is_url_boundary(b: u8) -> bool {
+    b.is_ascii_whitespace() || matches!(b, b'*' | b'_' | b'~' | b'(')
+}
+
+// 返回网址长度和链接地址
+fn scan_url(text: &str) -> Option<(usize, String)> {
+    let (prefix, scheme) = if text.starts_with("www.") {
+        (0, "http://")
+    } else if text.starts_with("https://") {
+        (8, "")
+    } else if text.starts_with("http://") {
+        (7, "")
+    } else {
+        return None;
+    };
+
+    let domain_len = scan_domain(&text[prefix..])?;
+    let mut end = prefix + domain_len;
+
+    // 路径部分直到空白或 <
+    end += text[end..]
+        .find(|c: char| c.is_whitespace() || c == '<')
+        .unwrap_or(text.len() - end);
+
+    let end = trim_trailing(&text[..end]);
+    Some((end, format!("{scheme}{}", &text[..end])))
+}
+
+// 至少包含一个 .，最后两段不能有下划线
+fn scan_domain(text: &str) -> Option<usize> {
+    let len = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(text.len());
+    let domain = text[..len].trim_end_matches('.');
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 || labels.iter().any(|label| label.is_empty()) {
+        return None;
+    }
+    if labels.iter().rev().take(2).any(|label| label.contains('_')) {
+        return None;
+    }
+
+    Some(domain.len())
+}
+
+// 去掉网址末尾的标点、多余的右括号和实体引用
+fn trim_trailing(url: &str) -> usize {
+    let mut end = url.len();
+    // 括号只统计一次，去掉右括号时同步减少
+    let opens = url.matches('(').count();
+    let mut closes = url.matches(')').count();
+
+    loop {
+        let current = &url[..end];
+        match current.as_bytes().last() {
+            Some(b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' | b'\'' | b'"') => end -= 1,
+            Some(b')') if closes > opens => {
+                closes -= 1;
+                end -= 1;
+            }
+            Some(b';') => {
+                let Some(amp) = current.rfind('&') else {
+                    break;
+                };
+                let name = &current[amp + 1..end - 1];
+                if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    break;
+                }
+                end = amp;
+            }
+            _ => break,
+        }
+    }
+
+    end
+}
+
+// 以 @ 为中心向两侧扫描邮箱，返回 (起点, 终点, 链接地址)
+fn scan_email(text: &str, min_start: usize, at: usize) -> Option<(usize, usize, String)> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'+');
+
+    let mut start = at;
+    while start > min_start && is_local(bytes[start - 1]) {
+        start -= 1;
+    }
+    if start == at {
+        return None;
+    }
+
+    let domain_start = at + 1;
+    let mut end = domain_start;
+    while end < bytes.len()
+        && (bytes[end].is_ascii_alphanumeric() || matches!(bytes[end], b'.' | b'-' | b'_'))
+    {
+        end += 1;
+    }
+
+    // 末尾的 . 不属于地址，末尾是 - 或 _ 时不是邮箱
+    while end > domain_start && bytes[end - 1] == b'.' {
+        end -= 1;
+    }
+    let domain = &text[domain_start..end];
+    if !domain.contains('.') || domain.ends_with(['-', '_']) {
+        return None;
+    }
+
+    Some((start, end, format!("mailto:{}", &text[start..end])))
+}
@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+
+use crate::ast::Inline;
+
+// 一串连续的 *、_ 或 ~ 及其能否开启、结束的判断结果
+pub struct DelimiterRun {
+    pub marker: u8,
+    pub count: usize,
+    pub can_open: bool,
+    pub can_close: bool,
+}
+
+enum Node<'a> {
+    Inline(Inline<'a>),
+    // 还可能作为开启符的分隔符，count 为剩余数量
+    Delimiter {
+        marker: u8,
+        count: usize,
+        orig_count: usize,
+        can_close: bool,
+    },
+}
+
+// CommonMark 的分隔符栈
+// 按从左到右的顺序处理结束符：找到开启符后，开启符之后的节点正好是 nodes 的尾部，
+// 直接 split_off 包成强调节点，因此不需要链表
+pub struct DelimiterStack<'a> {
+    nodes: Vec<Node<'a>>,
+    // nodes 中仍可作为开启符的分隔符下标，自底向上
+    openers: Vec<usize>,
+    // openers_bottom：按 (字符, 结束符能否开启, 原长度 % 3) 记录向下查找的下界，
+    // 下界以下不可能有匹配的开启符，保证病态输入下仍是线性
+    bottoms: [usize; 18],
+}
+
+impl<'a> DelimiterStack<'a> {
+    pub fn new() -> Self {
+        DelimiterStack {
+            nodes: Vec::new(),
+            openers: Vec::new(),
+            bottoms: [0; 18],
+        }
+    }
+
+    pub fn push_inline(&mut self, inline: Inline<'a>) {
+        self.nodes.push(Node::Inline(inline));
+    }
+
+    pub fn push_delimiter(&mut self, run: DelimiterRun) {
+        let mut count = run.count;
+
+        if run.can_close {
+            count = self.close(&run);
+        }
+
+        if count == 0 {
+            return;
+        }
+
+        if run.can_open {
+            self.openers.push(self.nodes.len());
+            self.nodes.push(Node::Delimiter {
+                marker: run.marker,
+                count,
+                orig_count: run.count,
+                can_close: run.can_close,
+            });
+        } else {
+            let text = (run.marker as char).to_string().repeat(count);
+            self.nodes
+                .push(Node::Inline(Inline::Text(Cow::Owned(text))));
+        }
+    }
+
+    pub fn finish(self) -> Vec<Inline<'a>> {
+        Self::into_inlines(self.nodes)
+    }
+
+    // 用结束符依次匹配开启符，返回结束符剩余数量
+    fn close(&mut self, closer: &DelimiterRun) -> usize {
+        let key = Self::bottom_key(closer);
+        let mut count = closer.count;
+
+        while count > 0 {
+            let Some(k) = self.find_opener(closer) else {
+                self.bottoms[key] = self.openers.len();
+                break;
+            };
+
+            // 开启符与结束符之间的分隔符不再参与匹配
+            self.truncate_openers(k + 1);
+
+            let opener_idx = self.openers[k];
+            let Node::Delimiter {
+                count: opener_count,
+                ..
+            } = &mut self.nodes[opener_idx]
+            else {
+                unreachable!("openers 只指向分隔符节点");
+            };
+
+            // 两边都至少有两个时优先组成 strong
+            let use_count = if closer.marker == b'~' || (count >= 2 && *opener_count >= 2) {
+                2
+            } else {
+                1
+            };
+            *opener_count -= use_count;
+            let opener_left = *opener_count;
+            count -= use_count;
+
+            let children = Self::into_inlines(self.nodes.split_off(opener_idx + 1));
+            let node = match (closer.marker, use_count) {
+                (b'~', _) => Inline::Strikethrough(children),
+                (_, 1) => Inline::Emphasis(children),
+                _ => Inline::Strong(children),
+            };
+
+            if opener_left == 0 {
+                self.nodes.pop();
+                self.truncate_openers(k);
+            }
+            self.nodes.push(Node::Inline(node));
+        }
+
+        count
+    }
+
+    fn find_opener(&self, closer: &DelimiterRun) -> Option<usize> {
+        let bottom = self.bottoms[Self::bottom_key(closer)];
+
+        (bottom..self.openers.len()).rev().find(|&k| {
+            let Node::Delimiter {
+                marker,
+                orig_count,
+                can_close,
+                ..
+            } = &self.nodes[self.openers[k]]
+            else {
+                return false;
+            };
+
+            if *marker != closer.marker {
+                return false;
+            }
+
+            // 三倍规则：能同时开闭的一方参与时，长度和为 3 的倍数则不匹配，除非两者都是 3 的倍数
+            let sum = orig_count + closer.count;
+            !((*can_close || closer.can_open)
+                && sum.is_multiple_of(3)
+                && !(orig_count.is_multiple_of(3) && closer.count.is_multiple_of(3)))
+        })
+    }
+
+    fn truncate_openers(&mut self, len: usize) {
+        self.openers.truncate(len);
+        for bottom in self.bottoms.iter_mut() {
+            *bottom = (*bottom).min(len);
+        }
+    }
+
+    fn bottom_key(run: &DelimiterRun) -> usize {
+        let marker = match run.marker {
+            b'*' => 0,
+            b'_' => 1,
+            _ => 2,
+        };
+        marker * 6 + (run.can_open as usize) * 3 + run.count % 3
+    }
+
+    // 剩余的分隔符还原成文本，并合并相邻文本
+    fn into_inlines(nodes: Vec<Node<'a>>) -> Vec<Inline<'a>> {
+        let mut inlines: Vec<Inline<'a>> = Vec::with_capacity(nodes.len());
+
+        for node in nodes {
+            let inline = match node {
+                Node::Inline(inline) => inline,
+                Node::Delimiter { marker, count, .. } => {
+                    Inline::Text(Cow::Owned((marker as char).to_string().repeat(count)))
+                }
+            };
+
+            match (inlines.last_mut(), inline) {
+                (Some(Inline::Text(last)), Inline::Text(text)) => last.to_mut().push_str(&text),
+                (_, inline) => inlines.push(inline),
+            }
+        }
+
+        inlines
+    }
+}
+
+enum Pending<'a> {
+    Inline(Inline<'a>),
+    Delimiter(DelimiterRun),
+}
+
+// 尚未匹配强调的行内节点
+// 方括号内的强调要等链接确定后才能匹配：链接闭合时只对其文本调用 finish，
+// 其余节点在解析结束时统一交给 DelimiterStack
+pub struct PendingInlines<'a> {
+    items: Vec<Pending<'a>>,
+}
+
+impl<'a> PendingInlines<'a> {
+    pub fn new() -> Self {
+        PendingInlines { items: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn push_inline(&mut self, inline: Inline<'a>) {
+        self.items.push(Pending::Inline(inline));
+    }
+
+    pub fn push_text(&mut self, buffer: &mut String) {
+        if !buffer.is_empty() {
+            self.push_inline(Inline::Text(Cow::Owned(std::mem::take(buffer))));
+        }
+    }
+
+    pub fn push_delimiter(&mut self, run: DelimiterRun) {
+        self.items.push(Pending::Delimiter(run));
+    }
+
+    // 取出 index 之后的节点，并去掉 index 处的节点（链接的开方括号）
+    pub fn take_after(&mut self, index: usize) -> PendingInlines<'a> {
+        let items = self.items.split_off(index + 1);
+        self.items.truncate(index);
+        PendingInlines { items }
+    }
+
+    pub fn finish(self) -> Vec<Inline<'a>> {
+        let mut stack = DelimiterStack::new();
+        for item in self.items {
+            match item {
+                Pending::Inline(inline) => stack.push_inline(inline),
+                Pending::Delimiter(run) => stack.push_delimiter(run),
+            }
+        }
+
+        stack.finish()
+    }
+}
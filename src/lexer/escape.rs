@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use crate::lexer::ENTITIES;
+
+// 处理反斜杠转义和实体引用，只有 ASCII 标点可以被转义
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains(['\\', '&']) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        match c {
+            '\\' if s[i + 1..].starts_with(|next: char| next.is_ascii_punctuation()) => {
+                out.push_str(&s[i + 1..i + 2]);
+                i += 2;
+            }
+            '&' if let Some((len, decoded)) = decode_entity(&s[i..]) => {
+                out.push_str(&decoded);
+                i += len;
+            }
+            _ => {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+// 解析 s 开头的 &name;、&#123; 或 &#x1F600;，返回消耗的字节数和对应文本
+// 不合法的引用返回 None，按普通文本处理
+pub fn decode_entity(s: &str) -> Option<(usize, Cow<'static, str>)> {
+    let rest = s.strip_prefix('&')?;
+
+    if let Some(number) = rest.strip_prefix('#') {
+        let (digits, radix, max_len) = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => (hex, 16, 6),
+            None => (number, 10, 7),
+        };
+
+        let len = digits
+            .bytes()
+            .take_while(|b| b.is_ascii_digit() || (radix == 16 && b.is_ascii_hexdigit()))
+            .count();
+        if len == 0 || len > max_len || digits.as_bytes().get(len) != Some(&b';') {
+            return None;
+        }
+
+        // 0、代理区和超出范围的码点替换为 U+FFFD
+        let code = u32::from_str_radix(&digits[..len], radix).ok()?;
+        let c = match code {
+            0 => char::REPLACEMENT_CHARACTER,
+            _ => char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+        };
+
+        let consumed = s.len() - digits.len() + len + 1;
+        return Some((consumed, Cow::Owned(c.to_string())));
+    }
+
+    let len = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .count();
+    if len == 0 || rest.as_bytes().get(len) != Some(&b';') {
+        return None;
+    }
+
+    let name = &rest[..len];
+    let index = ENTITIES.binary_search_by(|(n, _)| n.cmp(&name)).ok()?;
+    Some((len + 2, Cow::Borrowed(ENTITIES[index].1)))
+}
@@ -0,0 +1,147 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    ast::{Block, Inline},
+    lexer::normalize_label,
+};
+
+// 按首次引用的顺序给脚注编号，被引用的定义按编号移到文档末尾，未被引用的定义丢弃
+pub fn number_footnotes(blocks: &mut Vec<Block<'_>>) {
+    let mut definitions = HashMap::new();
+    take_definitions(blocks, &mut definitions);
+
+    let mut numbering = Numbering {
+        labels: Vec::new(),
+        numbers: HashMap::new(),
+        names: definitions
+            .iter()
+            .filter_map(|(key, definition)| match definition {
+                Block::FootnoteDefinition { label, .. } => Some((key.clone(), label.to_string())),
+                _ => None,
+            })
+            .collect(),
+    };
+    number_blocks(blocks, &mut numbering);
+
+    // 定义里的引用可能带来新的脚注，按编号依次处理
+    let mut footnotes = Vec::new();
+    while let Some(key) = numbering.labels.get(footnotes.len()).cloned() {
+        let Some(mut definition) = definitions.remove(&key) else {
+            break;
+        };
+        if let Block::FootnoteDefinition { children, .. } = &mut definition {
+            number_blocks(children, &mut numbering);
+        }
+        footnotes.push(definition);
+    }
+
+    for footnote in &mut footnotes {
+        if let Block::FootnoteDefinition {
+            label,
+            number,
+            references,
+            ..
+        } = footnote
+        {
+            (*number, *references) = numbering.numbers[&normalize_label(label)];
+        }
+    }
+
+    blocks.extend(footnotes);
+}
+
+struct Numbering {
+    // 按编号排列的规范化标签
+    labels: Vec<String>,
+    // 规范化标签 -> (编号, 引用次数)
+    numbers: HashMap<String, (usize, usize)>,
+    // 规范化标签 -> 定义中的标签，引用统一使用定义的写法
+    names: HashMap<String, String>,
+}
+
+impl Numbering {
+    // 记录一次引用，返回定义的标签、编号和这是第几次引用
+    fn reference(&mut self, label: &str) -> (Option<&str>, usize, usize) {
+        let key = normalize_label(label);
+        let next = self.labels.len() + 1;
+        let labels = &mut self.labels;
+
+        let (number, count) = self.numbers.entry(key.clone()).or_insert_with(|| {
+            labels.push(key.clone());
+            (next, 0)
+        });
+        *count += 1;
+
+        (self.names.get(&key).map(String::as_str), *number, *count)
+    }
+}
+
+fn take_definitions<'a>(blocks: &mut Vec<Block<'a>>, definitions: &mut HashMap<String, Block<'a>>) {
+    for mut block in std::mem::take(blocks) {
+        match &mut block {
+            Block::FootnoteDefinition { label, .. } => {
+                // 同名定义以第一个为准
+                definitions.entry(normalize_label(label)).or_insert(block);
+                continue;
+            }
+            Block::BlockQuote(children) => take_definitions(children, definitions),
+            Block::List { items, .. } => {
+                for item in items {
+                    take_definitions(&mut item.children, definitions);
+                }
+            }
+            _ => {}
+        }
+        blocks.push(block);
+    }
+}
+
+fn number_blocks(blocks: &mut [Block<'_>], numbering: &mut Numbering) {
+    for block in blocks {
+        match block {
+            Block::Heading { children, .. } | Block::Paragraph { children } => {
+                number_inlines(children, numbering);
+            }
+            Block::BlockQuote(children) | Block::FootnoteDefinition { children, .. } => {
+                number_blocks(children, numbering);
+            }
+            Block::List { items, .. } => {
+                for item in items {
+                    number_blocks(&mut item.children, numbering);
+                }
+            }
+            Block::Table { headers, rows, .. } => {
+                headers
+                    .iter_mut()
+                    .chain(rows.iter_mut().flatten())
+                    .for_each(|cell| number_inlines(cell, numbering));
+            }
+            Block::CodeBlock { .. } | Block::ThematicBreak | Block::HtmlBlock(_) => {}
+        }
+    }
+}
+
+fn number_inlines(inlines: &mut [Inline<'_>], numbering: &mut Numbering) {
+    for inline in inlines {
+        match inline {
+            Inline::FootnoteReference {
+                label,
+                number,
+                index,
+            } => {
+                let (name, n, i) = numbering.reference(label);
+                if let Some(name) = name
+                    && name != label.as_ref()
+                {
+                    *label = Cow::Owned(name.to_string());
+                }
+                (*number, *index) = (n, i);
+            }
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Link { children, .. } => number_inlines(children, numbering),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,314 @@
+// CommonMark 原始 HTML 的语法：开始标签、结束标签、注释、处理指令、声明和 CDATA
+// 各函数检查 input 开头，成功时返回匹配的字节数
+
+// 已经确定之后没有结束符的结构，同类的开启符不必再扫描到输入末尾
+#[derive(Default)]
+pub struct UnclosedHtml {
+    comment: bool,
+    processing_instruction: bool,
+    cdata: bool,
+    declaration: bool,
+}
+
+pub fn scan_html_tag(input: &str, unclosed: &mut UnclosedHtml) -> Option<usize> {
+    scan_open_tag(input)
+        .or_else(|| scan_closing_tag(input))
+        .or_else(|| scan_comment(input, &mut unclosed.comment))
+        .or_else(|| scan_processing_instruction(input, &mut unclosed.processing_instruction))
+        .or_else(|| scan_cdata(input, &mut unclosed.cdata))
+        .or_else(|| scan_declaration(input, &mut unclosed.declaration))
+}
+
+// <tag attr="value" attr2=value attr3 />
+pub fn scan_open_tag(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    if bytes.first() != Some(&b'<') {
+        return None;
+    }
+
+    let mut pos = 1 + tag_name_len(&bytes[1..])?;
+    loop {
+        let ws = whitespace_len(&bytes[pos..]);
+        match attribute_len(&bytes[pos + ws..]) {
+            // 属性之间必须有空白
+            Some(len) if ws > 0 => pos += ws + len,
+            _ => {
+                pos += ws;
+                break;
+            }
+        }
+    }
+
+    if bytes.get(pos) == Some(&b'/') {
+        pos += 1;
+    }
+
+    (bytes.get(pos) == Some(&b'>')).then_some(pos + 1)
+}
+
+// </tag>
+pub fn scan_closing_tag(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    if !input.starts_with("</") {
+        return None;
+    }
+
+    let mut pos = 2 + tag_name_len(&bytes[2..])?;
+    pos += whitespace_len(&bytes[pos..]);
+
+    (bytes.get(pos) == Some(&b'>')).then_some(pos + 1)
+}
+
+// <!-- ... -->，也接受 <!--> 和 <!--->
+fn scan_comment(input: &str, unclosed: &mut bool) -> Option<usize> {
+    let rest = input.strip_prefix("<!--")?;
+
+    if rest.starts_with('>') {
+        return Some(5);
+    }
+    if rest.starts_with("->") {
+        return Some(6);
+    }
+
+    find_closer(rest, "-->", unclosed).map(|end| 4 + end + 3)
+}
+
+// <? ... ?>
+fn scan_processing_instruction(input: &str, unclosed: &mut bool) -> Option<usize> {
+    let rest = input.strip_prefix("<?")?;
+    find_closer(rest, "?>", unclosed).map(|end| 2 + end + 2)
+}
+
+// <![CDATA[ ... ]]>
+fn scan_cdata(input: &str, unclosed: &mut bool) -> Option<usize> {
+    let rest = input.strip_prefix("<![CDATA[")?;
+    find_closer(rest, "]]>", unclosed).map(|end| 9 + end + 3)
+}
+
+// <!DOCTYPE html>：<! 后紧跟字母
+fn scan_declaration(input: &str, unclosed: &mut bool) -> Option<usize> {
+    let rest = input.strip_prefix("<!")?;
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    find_closer(rest, ">", unclosed).map(|end| 2 + end + 1)
+}
+
+// 后面的开启符只会看到更短的剩余输入，一次没找到结束符之后也不可能找到
+fn find_closer(rest: &str, closer: &str, unclosed: &mut bool) -> Option<usize> {
+    if *unclosed {
+        return None;
+    }
+
+    let end = rest.find(closer);
+    *unclosed = end.is_none();
+    end
+}
+
+// 字母开头，之后是字母、数字或 -
+fn tag_name_len(bytes: &[u8]) -> Option<usize> {
+    if !bytes.first()?.is_ascii_alphabetic() {
+        return None;
+    }
+
+    Some(
+        bytes
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+            .count(),
+    )
+}
+
+// 属性名及可选的 = 值
+fn attribute_len(bytes: &[u8]) -> Option<usize> {
+    let first = *bytes.first()?;
+    if !(first.is_ascii_alphabetic() || first == b'_' || first == b':') {
+        return None;
+    }
+
+    let name_len = bytes
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b':' | b'-'))
+        .count();
+
+    // 值是可选的，不合法时只算属性名
+    let mut pos = name_len;
+    pos += whitespace_len(&bytes[pos..]);
+    if bytes.get(pos) != Some(&b'=') {
+        return Some(name_len);
+    }
+    pos += 1;
+    pos += whitespace_len(&bytes[pos..]);
+
+    match attribute_value_len(&bytes[pos..]) {
+        Some(len) => Some(pos + len),
+        None => Some(name_len),
+    }
+}
+
+// 无引号、单引号或双引号的属性值
+fn attribute_value_len(bytes: &[u8]) -> Option<usize> {
+    match *bytes.first()? {
+        quote @ (b'"' | b'\'') => {
+            let end = bytes[1..].iter().position(|b| *b == quote)?;
+            Some(end + 2)
+        }
+        _ => {
+            let len = bytes
+                .iter()
+                .take_while(|b| !b.is_ascii_whitespace() && !b"\"'=<>`".contains(b))
+                .count();
+            (len > 0).then_some(len)
+        }
+    }
+}
+
+// 空格、制表符和换行
+fn whitespace_len(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take_while(|b| matches!(b, b' ' | b'\t' | b'\n'))
+        .count()
+}
+
+// HTML 块的结束条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlBlockEnd {
+    // <script>、<pre>、<style>、<textarea>：遇到对应的结束标签
+    RawTag,
+    // 注释、处理指令、声明和 CDATA：遇到结束标记
+    Marker(&'static str),
+    // 块级标签和其他完整标签：遇到空行
+    BlankLine,
+}
+
+impl HtmlBlockEnd {
+    // 当前行是否结束 HTML 块，结束行本身属于块
+    pub fn is_end(self, line: &str) -> bool {
+        match self {
+            HtmlBlockEnd::RawTag => {
+                let line = line.to_ascii_lowercase();
+                RAW_TAGS
+                    .iter()
+                    .any(|tag| line.contains(&format!("</{tag}>")))
+            }
+            HtmlBlockEnd::Marker(marker) => line.contains(marker),
+            HtmlBlockEnd::BlankLine => line.trim().is_empty(),
+        }
+    }
+}
+
+const RAW_TAGS: [&str; 4] = ["pre", "script", "style", "textarea"];
+
+const BLOCK_TAGS: [&str; 62] = [
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+// 判断去掉缩进后的行能否开始 HTML 块，返回结束条件和能否打断段落
+pub fn html_block_start(line: &str) -> Option<(HtmlBlockEnd, bool)> {
+    if !line.starts_with('<') {
+        return None;
+    }
+
+    for (start, end) in [("<!--", "-->"), ("<?", "?>"), ("<![CDATA[", "]]>")] {
+        if line.starts_with(start) {
+            return Some((HtmlBlockEnd::Marker(end), true));
+        }
+    }
+    if line.starts_with("<!") && line[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Some((HtmlBlockEnd::Marker(">"), true));
+    }
+
+    let is_closing = line.starts_with("</");
+    let name_start = if is_closing { 2 } else { 1 };
+    let name_len = tag_name_len(&line.as_bytes()[name_start..])?;
+    let name = line[name_start..name_start + name_len].to_ascii_lowercase();
+    let after = &line[name_start + name_len..];
+
+    if !is_closing
+        && RAW_TAGS.contains(&name.as_str())
+        && (after.is_empty() || after.starts_with([' ', '\t', '>']))
+    {
+        return Some((HtmlBlockEnd::RawTag, true));
+    }
+
+    if BLOCK_TAGS.contains(&name.as_str())
+        && (after.is_empty() || after.starts_with([' ', '\t', '>']) || after.starts_with("/>"))
+    {
+        return Some((HtmlBlockEnd::BlankLine, true));
+    }
+
+    // 单独一行的完整标签，不能打断段落
+    let tag_len = if is_closing {
+        scan_closing_tag(line)
+    } else {
+        scan_open_tag(line)
+    }?;
+    if !RAW_TAGS.contains(&name.as_str()) && line[tag_len..].trim().is_empty() {
+        return Some((HtmlBlockEnd::BlankLine, false));
+    }
+
+    None
+}
@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+
+use crate::{
+    ast::{CodeInfo, LineRange},
+    lexer::unescape,
+};
+
+// 解析代码围栏的 info string，例如:
+// rust title="main.rs" {1,3-5} .numbered
+// {.python #example linenos=true}
+pub struct InfoParser<'a> {
+    input: &'a str,
+    // 字节索引
+    pos: usize,
+}
+
+impl<'a> InfoParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        InfoParser {
+            input: input.trim(),
+            pos: 0,
+        }
+    }
+
+    // 返回语言和其余属性
+    pub fn parse(&mut self) -> (Cow<'a, str>, CodeInfo<'a>) {
+        let mut language = Cow::Borrowed("");
+        let mut info = CodeInfo::default();
+
+        // 第一个普通单词是语言
+        let first = self.peek_token();
+        if !first.is_empty() && !first.contains('=') && !first.starts_with(['.', '#', '{']) {
+            language = unescape(first);
+            self.pos += first.len();
+        }
+
+        self.parse_attributes(&mut info, None);
+
+        // pandoc 风格 {.rust} 只写了 class
+        if language.is_empty() && !info.classes.is_empty() {
+            language = info.classes.remove(0);
+        }
+
+        (language, info)
+    }
+
+    fn parse_attributes(&mut self, info: &mut CodeInfo<'a>, until: Option<u8>) {
+        loop {
+            self.skip_whitespace();
+
+            let Some(c) = self.current_byte() else {
+                return;
+            };
+
+            if Some(c) == until {
+                self.pos += 1;
+                return;
+            }
+
+            if c == b'{' && until.is_none() {
+                self.pos += 1;
+                self.parse_brace_group(info);
+                continue;
+            }
+
+            let token = self.peek_token();
+            if token.is_empty() {
+                // 孤立的 { 或 }，直接跳过
+                self.pos += self.current_char().map_or(1, char::len_utf8);
+                continue;
+            }
+            self.pos += token.len();
+
+            if let Some(class) = token.strip_prefix('.') {
+                if !class.is_empty() {
+                    info.classes.push(unescape(class));
+                }
+            } else if let Some(id) = token.strip_prefix('#') {
+                if !id.is_empty() {
+                    info.attributes.push((Cow::Borrowed("id"), unescape(id)));
+                }
+            } else if let Some((key, value)) = token.split_once('=') {
+                if !key.is_empty() {
+                    info.attributes
+                        .push((Cow::Borrowed(key), Self::unquote(value)));
+                }
+            } else {
+                info.classes.push(unescape(token));
+            }
+        }
+    }
+
+    // { 之后：纯数字范围是高亮行，否则按属性列表解析
+    fn parse_brace_group(&mut self, info: &mut CodeInfo<'a>) {
+        let rest = &self.input[self.pos..];
+        let end = rest.find('}').unwrap_or(rest.len());
+        let content = &rest[..end];
+
+        let is_ranges = !content.trim().is_empty()
+            && content
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ',' || c == '-' || c == ' ');
+
+        if is_ranges {
+            info.highlight_lines.extend(Self::parse_ranges(content));
+            self.pos += (end + 1).min(rest.len());
+        } else {
+            self.parse_attributes(info, Some(b'}'));
+        }
+    }
+
+    fn parse_ranges(content: &str) -> Vec<LineRange> {
+        content
+            .split(',')
+            .filter_map(|part| {
+                let part = part.trim();
+                let (start, end) = part.split_once('-').unwrap_or((part, part));
+                let start = start.trim().parse().ok()?;
+                let end = end.trim().parse().ok()?;
+
+                if start <= end {
+                    Some(LineRange { start, end })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // 取下一个 token，引号内的空白和 {} 不作为分隔
+    fn peek_token(&self) -> &'a str {
+        let rest = &self.input[self.pos..];
+        let mut chars = rest.char_indices().peekable();
+        let mut quote = None;
+        let mut prev = None;
+
+        while let Some((i, c)) = chars.next() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c.is_whitespace() || c == '{' || c == '}' => return &rest[..i],
+                None if (c == '"' || c == '\'') && prev == Some('=') => quote = Some(c),
+                None => {}
+            }
+
+            // 转义字符连同下一个字符一起跳过
+            if c == '\\' {
+                chars.next();
+            }
+            prev = Some(c);
+        }
+
+        rest
+    }
+
+    fn unquote(value: &'a str) -> Cow<'a, str> {
+        for q in ['"', '\''] {
+            if let Some(inner) = value.strip_prefix(q) {
+                return unescape(inner.strip_suffix(q).unwrap_or(inner));
+            }
+        }
+
+        unescape(value)
+    }
+
+    fn current_byte(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn current_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    // 与 peek_token 使用同样的空白判断，包括全角空格等非 ASCII 空白
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.current_char().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+}
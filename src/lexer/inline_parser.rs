@@ -1,19 +1,63 @@
-use crate::ast::Inline;
-use std::borrow::Cow;
+use crate::{
+    ast::Inline,
+    lexer::{
+        DelimiterRun, LinkParser, ParseOptions, PendingInlines, RefMap, UnclosedHtml,
+        decode_entity, is_link_label, link_extended, normalize_label, parse_autolink,
+        scan_html_tag,
+    },
+};
+use std::{borrow::Cow, collections::HashSet};
 
 pub struct InlineParser<'a> {
     input: &'a str,
     // 字节索引
     pos: usize,
+    options: ParseOptions,
+    refs: Option<&'a RefMap>,
+    // 已定义的脚注标签，规范化后
+    footnotes: Option<&'a HashSet<String>>,
+    unclosed_html: UnclosedHtml,
+}
+
+// 链接目标、标题和在输入中的结束位置
+type LinkTarget<'a> = (Cow<'a, str>, Option<Cow<'a, str>>, usize);
+
+// [ 或 ![ 开启符
+struct Bracket {
+    // 开方括号文本在 PendingInlines 中的位置
+    index: usize,
+    // 链接文本在输入中的起点
+    start: usize,
+    image: bool,
+    // 链接不能嵌套，内层链接闭合后外层的 [ 失效
+    active: bool,
 }
 
 impl<'a> InlineParser<'a> {
-    pub fn new(input: &'a str) -> Self {
-        InlineParser { input, pos: 0 }
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        InlineParser {
+            input,
+            pos: 0,
+            options,
+            refs: None,
+            footnotes: None,
+            unclosed_html: UnclosedHtml::default(),
+        }
+    }
+
+    pub fn with_refs(mut self, refs: &'a RefMap) -> Self {
+        self.refs = Some(refs);
+        self
+    }
+
+    pub fn with_footnotes(mut self, footnotes: &'a HashSet<String>) -> Self {
+        self.footnotes = Some(footnotes);
+        self
     }
 
     pub fn parse(&mut self) -> Vec<Inline<'a>> {
-        let mut inlines = Vec::new();
+        let mut stack = PendingInlines::new();
+        let mut brackets: Vec<Bracket> = Vec::new();
         let mut text_buffer = String::new();
 
         while !self.eof() {
@@ -24,23 +68,46 @@ impl<'a> InlineParser<'a> {
 
             match current_char {
                 // === 1. 转义字符 ===
+                // 行尾的反斜杠是硬换行
+                '\\' if self.peek_char() == Some('\n') => {
+                    stack.push_text(&mut text_buffer);
+                    stack.push_inline(Inline::HardBreak);
+                    self.pos += 2;
+                    self.skip_line_indent();
+                }
                 '\\' => {
-                    if let Some(next_char) = self.peek_char() {
-                        if Self::is_special_char(next_char) {
-                            text_buffer.push(next_char);
-                            self.pos += '\\'.len_utf8() + next_char.len_utf8();
-                            continue;
-                        }
+                    if let Some(next_char) = self.peek_char()
+                        && Self::is_special_char(next_char)
+                    {
+                        text_buffer.push(next_char);
+                        self.pos += '\\'.len_utf8() + next_char.len_utf8();
+                        continue;
                     }
                     text_buffer.push('\\');
                     self.pos += '\\'.len_utf8();
                 }
+                // 实体引用解码后是普通文本，不再作为分隔符
+                '&' => match decode_entity(&self.input[self.pos..]) {
+                    Some((len, decoded)) => {
+                        text_buffer.push_str(&decoded);
+                        self.pos += len;
+                    }
+                    None => {
+                        text_buffer.push('&');
+                        self.pos += '&'.len_utf8();
+                    }
+                },
 
-                // === 2. HTML 标签 ===
+                // === 2. 自动链接与 HTML 标签 ===
                 '<' => {
-                    if let Some(html_node) = self.try_parse_html_tag() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(html_node);
+                    if let Some(link_node) = self.try_parse_autolink() {
+                        stack.push_text(&mut text_buffer);
+                        stack.push_inline(link_node);
+                    } else if self.options.raw_html
+                        && let Some(html_node) = self.try_parse_html_tag()
+                    {
+                        stack.push_text(&mut text_buffer);
+                        stack.push_inline(html_node);
                     } else {
                         text_buffer.push('<');
                         self.pos += '<'.len_utf8();
@@ -49,60 +116,73 @@ impl<'a> InlineParser<'a> {
 
                 // === 3. 图片 (Image) ===
                 '!' if self.peek_char() == Some('[') => {
-                    if let Some(img_node) = self.try_parse_image() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(img_node);
-                    } else {
-                        text_buffer.push('!');
-                        self.pos += '!'.len_utf8();
-                    }
+                    self.open_bracket(&mut stack, &mut brackets, &mut text_buffer, true);
                 }
 
                 // === 4. 链接 (Link) ===
                 '[' => {
-                    if let Some(link_node) = self.try_parse_link() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(link_node);
+                    if let Some(footnote) = self.try_parse_footnote_reference() {
+                        stack.push_text(&mut text_buffer);
+                        stack.push_inline(footnote);
                     } else {
-                        text_buffer.push('[');
-                        self.pos += '['.len_utf8();
+                        self.open_bracket(&mut stack, &mut brackets, &mut text_buffer, false);
                     }
                 }
+                ']' => self.close_bracket(&mut stack, &mut brackets, &mut text_buffer),
 
                 // === 5. 行内代码 (CodeSpan) ===
                 '`' => {
                     if let Some(node) = self.try_parse_code_span() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(node);
+                        stack.push_text(&mut text_buffer);
+                        stack.push_inline(node);
                     } else {
-                        text_buffer.push('`');
-                        self.pos += '`'.len_utf8();
+                        // 没有闭合时整串反引号都是普通文本
+                        let count = self.count_delimiter('`');
+                        text_buffer.push_str(&self.input[self.pos..self.pos + count]);
+                        self.pos += count;
                     }
                 }
 
                 // === 6. 强调/粗体 (Emphasis/Strong) ===
-                '*' => {
-                    if let Some(node) = self.try_parse_emphasis() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(node);
-                    } else {
-                        text_buffer.push('*');
-                        self.pos += '*'.len_utf8();
-                    }
+                '*' | '_' => {
+                    let run = self.scan_delimiter_run(current_char as u8);
+                    self.pos += run.count;
+                    stack.push_text(&mut text_buffer);
+                    stack.push_delimiter(run);
                 }
 
                 // === 7. 删除线 (Strikethrough) ===
+                // 只有恰好两个 ~ 才是分隔符
+                '~' if self.options.strikethrough && self.count_delimiter('~') == 2 => {
+                    let run = self.scan_delimiter_run(b'~');
+                    self.pos += run.count;
+                    stack.push_text(&mut text_buffer);
+                    stack.push_delimiter(run);
+                }
                 '~' => {
-                    if let Some(node) = self.try_parse_strikethrough() {
-                        Self::flush_text(&mut inlines, &mut text_buffer);
-                        inlines.push(node);
+                    let count = self.count_delimiter('~');
+                    text_buffer.push_str(&self.input[self.pos..self.pos + count]);
+                    self.pos += count;
+                }
+
+                // === 8. 换行 ===
+                // 行尾两个以上空格是硬换行，其余行尾空格丢弃
+                '\n' => {
+                    let content_len = text_buffer.trim_end_matches(' ').len();
+                    let is_hard = text_buffer.len() - content_len >= 2;
+                    text_buffer.truncate(content_len);
+
+                    stack.push_text(&mut text_buffer);
+                    stack.push_inline(if is_hard {
+                        Inline::HardBreak
                     } else {
-                        text_buffer.push('~');
-                        self.pos += '~'.len_utf8();
-                    }
+                        Inline::SoftBreak
+                    });
+                    self.pos += 1;
+                    self.skip_line_indent();
                 }
 
-                // === 8. 普通字符 ===
+                // === 9. 普通字符 ===
                 _ => {
                     text_buffer.push(current_char);
                     self.pos += current_char.len_utf8();
@@ -110,89 +190,65 @@ impl<'a> InlineParser<'a> {
             }
         }
 
-        Self::flush_text(&mut inlines, &mut text_buffer);
+        stack.push_text(&mut text_buffer);
 
-        inlines
+        let inlines = stack.finish();
+        if self.options.autolinks {
+            link_extended(inlines)
+        } else {
+            inlines
+        }
     }
 
+    // 所有 ASCII 标点都可以被反斜杠转义
     fn is_special_char(c: char) -> bool {
-        matches!(
-            c,
-            '\\' | '`'
-                | '*'
-                | '_'
-                | '{'
-                | '}'
-                | '['
-                | ']'
-                | '('
-                | ')'
-                | '#'
-                | '+'
-                | '-'
-                | '.'
-                | '!'
-                | '|'
-                | '>'
-                | '~'
-        )
+        c.is_ascii_punctuation()
     }
 
-    // 解析*和**
-    fn try_parse_emphasis(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-
-        let delimiter_count = self.count_delimiter('*');
-
-        // 修改点 A: 允许最多 3 个 (即 ***)
-        if delimiter_count > 3 {
-            self.pos = start_pos;
-            return None;
+    // 根据前后字符判断分隔符串能否开启、结束强调
+    fn scan_delimiter_run(&self, marker: u8) -> DelimiterRun {
+        let count = self.count_delimiter(marker as char);
+        // 行首行尾视为空白
+        let before = self.input[..self.pos].chars().next_back().unwrap_or(' ');
+        let after = self.input[self.pos + count..].chars().next().unwrap_or(' ');
+
+        let left_flanking = !after.is_whitespace()
+            && (!Self::is_punctuation(after)
+                || before.is_whitespace()
+                || Self::is_punctuation(before));
+        let right_flanking = !before.is_whitespace()
+            && (!Self::is_punctuation(before)
+                || after.is_whitespace()
+                || Self::is_punctuation(after));
+
+        // _ 不能用于单词内部：snake_case_name 保持原样
+        let (can_open, can_close) = if marker == b'_' {
+            (
+                left_flanking && (!right_flanking || Self::is_punctuation(before)),
+                right_flanking && (!left_flanking || Self::is_punctuation(after)),
+            )
+        } else {
+            (left_flanking, right_flanking)
+        };
+
+        DelimiterRun {
+            marker,
+            count,
+            can_open,
+            can_close,
         }
+    }
 
-        let bytes = self.input.as_bytes();
-
-        // 2. 处理 * 闭合
-        let content_start = start_pos + delimiter_count;
-        let mut current_search_pos = content_start;
-
-        while current_search_pos < self.input.len() {
-            if bytes[current_search_pos] == b'*' {
-                let mut close_count = 0;
-                let mut temp_pos = current_search_pos;
-                while temp_pos < self.input.len() && bytes[temp_pos] == b'*' {
-                    close_count += 1;
-                    temp_pos += 1;
-                }
+    // ASCII 标点，以及非字母数字的其他非空白字符（中文标点等）
+    fn is_punctuation(c: char) -> bool {
+        c.is_ascii_punctuation() || (!c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace())
+    }
 
-                if close_count == delimiter_count {
-                    // 前后 * 个数相匹配
-                    let content_str = &self.input[content_start..current_search_pos];
-
-                    // 递归下降分析中间内容
-                    let mut child_parser = InlineParser::new(&content_str);
-                    let children = child_parser.parse();
-
-                    self.pos = temp_pos;
-
-                    match delimiter_count {
-                        1 => return Some(Inline::Emphasis(children)),
-                        2 => return Some(Inline::Strong(children)),
-                        3 => {
-                            let inner_emph = Inline::Emphasis(children);
-                            return Some(Inline::Strong(vec![inner_emph]));
-                        }
-                        _ => return None,
-                    }
-                }
-                current_search_pos = temp_pos;
-            } else {
-                current_search_pos += 1;
-            }
+    // 下一行开头的空格不属于内容
+    fn skip_line_indent(&mut self) {
+        while self.current_char() == Some(' ') {
+            self.pos += 1;
         }
-
-        self.pos = start_pos;
-        None
     }
 
     fn count_delimiter(&self, char_to_match: char) -> usize {
@@ -212,13 +268,6 @@ impl<'a> InlineParser<'a> {
         count
     }
 
-    fn flush_text(inlines: &mut Vec<Inline<'a>>, buffer: &mut String) {
-        if !buffer.is_empty() {
-            inlines.push(Inline::Text(Cow::from(buffer.clone())));
-            buffer.clear();
-        }
-    }
-
     fn current_char(&self) -> Option<char> {
         self.input[self.pos..].chars().next()
     }
@@ -233,153 +282,200 @@ impl<'a> InlineParser<'a> {
         self.pos >= self.input.len()
     }
 
-    fn try_parse_image(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-        self.pos += 1; // 移动到[
-
-        if let Some((label, url)) = self.parse_bracket_link() {
-            return Some(Inline::Image {
-                alt: Cow::from(label),
-                url: Cow::from(url),
-            });
-        }
-
-        // 失败回退
-        self.pos = start_pos;
-        None
+    fn open_bracket(
+        &mut self,
+        stack: &mut PendingInlines<'a>,
+        brackets: &mut Vec<Bracket>,
+        text_buffer: &mut String,
+        image: bool,
+    ) {
+        let marker = if image { "![" } else { "[" };
+
+        stack.push_text(text_buffer);
+        brackets.push(Bracket {
+            index: stack.len(),
+            start: self.pos + marker.len(),
+            image,
+            active: true,
+        });
+        stack.push_inline(Inline::Text(Cow::Borrowed(marker)));
+        self.pos += marker.len();
     }
 
-    fn try_parse_link(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-
-        if let Some((label, url)) = self.parse_bracket_link() {
-            let mut inner_parser = InlineParser::new(&label);
-            let children = inner_parser.parse();
+    // ] 与最近的开启符配对，后面是链接目标或引用时构成链接，否则按普通文本处理
+    fn close_bracket(
+        &mut self,
+        stack: &mut PendingInlines<'a>,
+        brackets: &mut Vec<Bracket>,
+        text_buffer: &mut String,
+    ) {
+        let target = brackets
+            .pop()
+            .filter(|bracket| bracket.active)
+            .and_then(|bracket| {
+                let label = &self.input[bracket.start..self.pos];
+                let (url, title, end) = self.parse_link_target(label, self.pos + 1)?;
+                Some((bracket, url, title, end))
+            });
 
-            return Some(Inline::Link {
+        let Some((bracket, url, title, end)) = target else {
+            text_buffer.push(']');
+            self.pos += ']'.len_utf8();
+            return;
+        };
+
+        stack.push_text(text_buffer);
+        let children = stack.take_after(bracket.index).finish();
+        let url = self.filter_url(url, bracket.image);
+
+        if bracket.image {
+            // 图片描述里可以有链接，alt 取纯文本
+            stack.push_inline(Inline::Image {
+                alt: Cow::Owned(Self::plain_text(&children)),
+                url,
+                title,
+            });
+        } else {
+            stack.push_inline(Inline::Link {
                 children,
-                url: Cow::from(url),
+                url,
+                title,
             });
+            for bracket in brackets.iter_mut().filter(|bracket| !bracket.image) {
+                bracket.active = false;
+            }
         }
 
-        self.pos = start_pos;
-        None
+        self.pos = end;
     }
 
-    fn parse_bracket_link(&mut self) -> Option<(&'a str, &'a str)> {
-        let bytes = self.input.as_bytes();
-
-        // [开头
-        if self.pos >= self.input.len() || bytes[self.pos] != b'[' {
+    // [^label]，只有已定义的标签才是脚注引用，编号在解析结束后统一分配
+    fn try_parse_footnote_reference(&mut self) -> Option<Inline<'a>> {
+        if !self.options.footnotes {
             return None;
         }
 
-        // 预扫描[]是否闭合
-        let start_bracket_pos = self.pos;
-        let mut end_bracket_pos = start_bracket_pos + 1;
-
-        while end_bracket_pos < self.input.len() && bytes[end_bracket_pos] != b']' {
-            end_bracket_pos += 1;
-        }
-
-        if end_bracket_pos >= self.input.len() {
+        let rest = self.input[self.pos..].strip_prefix("[^")?;
+        // 标签里不能有空白和 [，遇到它们就可以停止查找
+        let end = rest.find(|c: char| c == ']' || c == '[' || c.is_whitespace())?;
+        let label = &rest[..end];
+        if label.is_empty() || !rest[end..].starts_with(']') {
             return None;
         }
-
-        if end_bracket_pos + 1 >= self.input.len() || bytes[end_bracket_pos + 1] != b'(' {
+        if !self.footnotes?.contains(&normalize_label(label)) {
             return None;
         }
 
-        // 匹配)
-        let start_paren_pos = end_bracket_pos + 1;
-        let mut end_paren_pos = start_paren_pos + 1;
-
-        while end_paren_pos < self.input.len() && bytes[end_paren_pos] != b')' {
-            end_paren_pos += 1;
-        }
+        self.pos += "[^".len() + end + "]".len();
+        Some(Inline::FootnoteReference {
+            label: Cow::Borrowed(label),
+            number: 0,
+            index: 0,
+        })
+    }
 
-        if end_paren_pos >= self.input.len() {
-            return None;
+    pub(crate) fn plain_text(inlines: &[Inline]) -> String {
+        let mut output = String::new();
+
+        for inline in inlines {
+            match inline {
+                Inline::Text(text) | Inline::CodeSpan(text) => output.push_str(text),
+                Inline::Emphasis(children)
+                | Inline::Strong(children)
+                | Inline::Strikethrough(children)
+                | Inline::Link { children, .. } => output.push_str(&Self::plain_text(children)),
+                Inline::Image { alt, .. } => output.push_str(alt),
+                Inline::SoftBreak | Inline::HardBreak => output.push('\n'),
+                Inline::RawHtml(_) | Inline::FootnoteReference { .. } => {}
+            }
         }
 
-        // 提取内容
-        let label = &self.input[start_bracket_pos + 1..end_bracket_pos];
+        output
+    }
 
-        let url = &self.input[start_paren_pos + 1..end_paren_pos];
+    // ] 之后的 (url "title")、[ref]、[] 或简写引用，返回目标、标题和结束位置
+    fn parse_link_target(
+        &self,
+        label: &'a str,
+        after_bracket: usize,
+    ) -> Option<LinkTarget<'a>> {
+        // 1. 行内链接 [text](url "title")
+        let mut link_parser = LinkParser::new(&self.input[after_bracket..]);
+        if let Some((url, title)) = link_parser.parse_inline_link() {
+            return Some((url, title, after_bracket + link_parser.pos()));
+        }
 
-        self.pos = end_paren_pos + 1;
-        Some((label, url))
+        // 2. 引用链接 [text][ref]、[ref][] 与 [ref]
+        self.parse_link_reference(label, after_bracket)
     }
 
-    fn try_parse_code_span(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-        let c = self.current_char()?;
-        let delimiter_count = self.count_delimiter(c);
+    // 在 ] 之后查找引用，返回目标、标题和结束位置
+    fn parse_link_reference(
+        &self,
+        label: &'a str,
+        after_bracket: usize,
+    ) -> Option<LinkTarget<'a>> {
+        let refs = self.refs?;
+        let rest = &self.input[after_bracket..];
+        // 简写和 [] 形式以链接文本作标签，同样要符合标签的限制
+        let shortcut = |end_pos| is_link_label(label).then_some((label, end_pos));
+
+        let (ref_label, end_pos) = if rest.starts_with("[]") {
+            shortcut(after_bracket + 2)?
+        } else {
+            let mut link_parser = LinkParser::new(rest);
+            match link_parser.parse_label() {
+                Some(ref_label) => (ref_label, after_bracket + link_parser.pos()),
+                // 后面紧跟 [ 但不是合法标签时不能当作简写形式
+                None if rest.starts_with('[') && rest[1..].contains(']') => return None,
+                None => shortcut(after_bracket)?,
+            }
+        };
 
-        let content_start = start_pos + delimiter_count;
-        let mut cur_search_pos = content_start;
+        let definition = refs.get(&normalize_label(ref_label))?;
+        let url = Cow::Owned(definition.url.clone());
+        let title = definition.title.clone().map(Cow::Owned);
 
-        let bytes = self.input.as_bytes();
-        while cur_search_pos < self.input.len() {
-            if bytes[cur_search_pos] == b'`' {
-                let mut close_count = 0;
-                let mut temp_pos = cur_search_pos;
-
-                while temp_pos < self.input.len() && bytes[temp_pos] == b'`' {
-                    close_count += 1;
-                    temp_pos += 1;
-                }
+        Some((url, title, end_pos))
+    }
 
-                // 前后`数量匹配
-                if close_count == delimiter_count {
-                    let content = &self.input[content_start..cur_search_pos];
+    fn try_parse_code_span(&mut self) -> Option<Inline<'a>> {
+        let (content_start, content_end, end) = self.find_code_span(self.pos)?;
+        let content = &self.input[content_start..content_end];
 
-                    self.pos = temp_pos;
-                    return Some(Inline::CodeSpan(Cow::Borrowed(content)));
-                }
+        self.pos = end;
 
-                cur_search_pos = temp_pos;
-            } else {
-                cur_search_pos += 1;
-            }
+        // 代码内的换行按空格处理
+        if content.contains('\n') {
+            return Some(Inline::CodeSpan(Cow::Owned(content.replace('\n', " "))));
         }
-
-        self.pos = start_pos;
-        None
+        Some(Inline::CodeSpan(Cow::Borrowed(content)))
     }
 
-    fn try_parse_strikethrough(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-        let delimiter_count = self.count_delimiter('~');
-
-        if delimiter_count != 2 {
-            self.pos = start_pos;
-            return None;
-        }
-
+    // 从 start 处的反引号串开始查找等长的闭合串，返回内容区间和结束位置
+    fn find_code_span(&self, start: usize) -> Option<(usize, usize, usize)> {
         let bytes = self.input.as_bytes();
+        let mut delimiter_count = 0;
+        while start + delimiter_count < bytes.len() && bytes[start + delimiter_count] == b'`' {
+            delimiter_count += 1;
+        }
 
-        let content_start = start_pos + delimiter_count;
+        let content_start = start + delimiter_count;
         let mut cur_search_pos = content_start;
 
-        while cur_search_pos < self.input.len() {
-            if bytes[cur_search_pos] == b'~' {
+        while cur_search_pos < bytes.len() {
+            if bytes[cur_search_pos] == b'`' {
                 let mut close_count = 0;
                 let mut temp_pos = cur_search_pos;
 
-                while temp_pos < self.input.len() && bytes[temp_pos] == b'~' {
+                while temp_pos < bytes.len() && bytes[temp_pos] == b'`' {
                     close_count += 1;
                     temp_pos += 1;
                 }
 
-                if close_count == 2 {
-                    let content = &self.input[content_start..cur_search_pos];
-                    let mut child_parser = InlineParser::new(content);
-                    let children = child_parser.parse();
-
-                    self.pos = temp_pos;
-
-                    return Some(Inline::Strikethrough(children));
+                // 前后`数量匹配
+                if close_count == delimiter_count {
+                    return Some((content_start, cur_search_pos, temp_pos));
                 }
 
                 cur_search_pos = temp_pos;
@@ -388,48 +484,61 @@ impl<'a> InlineParser<'a> {
             }
         }
 
-        self.pos = start_pos;
         None
     }
 
-    fn try_parse_html_tag(&mut self) -> Option<Inline<'a>> {
-        let start_pos = self.pos;
-        let bytes = self.input.as_bytes();
-
-        if bytes[self.pos] != b'<' {
-            return None;
-        }
+    // <https://example.com> 和 <user@example.com>
+    fn try_parse_autolink(&mut self) -> Option<Inline<'a>> {
+        let (len, url, text) = parse_autolink(&self.input[self.pos..])?;
+        self.pos += len;
 
-        let mut end_pos = start_pos + 1;
-        while end_pos < self.input.len() {
-            if bytes[end_pos] == b'>' {
-                break;
-            }
-            end_pos += 1;
-        }
+        Some(Inline::Link {
+            children: vec![Inline::Text(Cow::Borrowed(text))],
+            url: self.filter_url(url, false),
+            title: None,
+        })
+    }
 
-        if end_pos >= self.input.len() {
-            return None; // 没有闭合的 >
-        }
+    fn try_parse_html_tag(&mut self) -> Option<Inline<'a>> {
+        let len = scan_html_tag(&self.input[self.pos..], &mut self.unclosed_html)?;
+        let tag_content = &self.input[self.pos..self.pos + len];
 
-        let tag_content = &self.input[start_pos..=end_pos];
+        self.pos += len;
+        Some(Inline::RawHtml(Cow::Borrowed(tag_content)))
+    }
 
-        // 4. 简单验证看起来像不像标签
-        // 规则：< 后面必须紧跟字母、/ 或 ! (注释 <!--)
-        if tag_content.len() < 3 {
-            return None;
-        }
-        let second_char = tag_content.chars().nth(1).unwrap();
-        if !second_char.is_alphanumeric()
-            && second_char != '/'
-            && second_char != '!'
-            && second_char != '?'
-        {
-            return None;
+    // safe_urls 开启时把危险协议的链接清空，链接文本照常输出
+    fn filter_url(&self, url: Cow<'a, str>, image: bool) -> Cow<'a, str> {
+        if self.options.safe_urls && !is_safe_url(&url, image) {
+            Cow::Borrowed("")
+        } else {
+            url
         }
+    }
+}
 
-        // 5. 成功
-        self.pos = end_pos + 1;
-        Some(Inline::RawHtml(Cow::from(tag_content)))
+// 浏览器会忽略开头的空白控制字符和协议名中的 \t \n \r，判断协议前先去掉
+fn is_safe_url(url: &str, image: bool) -> bool {
+    let url = url.trim_start_matches(|c: char| c <= ' ');
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return true;
+    };
+
+    let scheme: String = scheme
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    match scheme.as_str() {
+        "javascript" | "vbscript" | "file" => false,
+        "data" => {
+            let rest = rest.to_ascii_lowercase();
+            image
+                && ["image/png", "image/gif", "image/jpeg", "image/webp"]
+                    .iter()
+                    .any(|mime| rest.starts_with(mime))
+        }
+        _ => true,
     }
 }
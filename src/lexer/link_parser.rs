@@ -0,0 +1,294 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::lexer::unescape;
+
+#[derive(Debug, Clone)]
+pub struct LinkDefinition {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+// 规范化后的标签 -> 定义
+pub type RefMap = HashMap<String, LinkDefinition>;
+
+// 标签比较前：去掉首尾空白，连续空白合并为一个空格，再做大小写折叠
+pub fn normalize_label(label: &str) -> String {
+    let collapsed = label.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapsed.to_lowercase().to_uppercase()
+}
+
+// 标签不能含未转义的方括号，最长 999 字节，不能全是空白
+pub fn is_link_label(label: &str) -> bool {
+    if label.len() > 999 || label.trim().is_empty() {
+        return false;
+    }
+
+    let bytes = label.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'[' | b']' => return false,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    true
+}
+
+// 解析链接的标签、目标、标题以及 [label]: url "title" 形式的定义
+pub struct LinkParser<'a> {
+    input: &'a str,
+    // 字节索引
+    pos: usize,
+}
+
+impl<'a> LinkParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        LinkParser { input, pos: 0 }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    // [label]：不能含未转义的方括号，最长 999 字符，不能全是空白
+    pub fn parse_label(&mut self) -> Option<&'a str> {
+        let bytes = self.input.as_bytes();
+        if bytes.get(self.pos) != Some(&b'[') {
+            return None;
+        }
+
+        let start = self.pos + 1;
+        let mut i = start;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 1,
+                b'[' => return None,
+                b']' => break,
+                _ => {}
+            }
+            i += 1;
+        }
+
+        if i >= bytes.len() {
+            return None;
+        }
+
+        let label = &self.input[start..i];
+        if !is_link_label(label) {
+            return None;
+        }
+
+        self.pos = i + 1;
+        Some(label)
+    }
+
+    // <...> 形式可以含空格；否则括号需配对，遇到空白或控制字符结束
+    pub fn parse_destination(&mut self) -> Option<Cow<'a, str>> {
+        let bytes = self.input.as_bytes();
+        let start = self.pos;
+
+        if bytes.get(start) == Some(&b'<') {
+            let mut i = start + 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 1,
+                    b'\n' | b'<' => return None,
+                    b'>' => {
+                        self.pos = i + 1;
+                        return Some(unescape(&self.input[start + 1..i]));
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut i = start;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if bytes.get(i + 1).is_some_and(|b| b.is_ascii_punctuation()) => i += 1,
+                b'(' => {
+                    depth += 1;
+                    if depth > 32 {
+                        return None;
+                    }
+                }
+                b')' => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                b if b <= b' ' || b == 0x7f => break,
+                _ => {}
+            }
+            i += 1;
+        }
+
+        if i == start || depth != 0 {
+            return None;
+        }
+
+        self.pos = i;
+        Some(unescape(&self.input[start..i]))
+    }
+
+    // "title"、'title' 或 (title)
+    pub fn parse_title(&mut self) -> Option<Cow<'a, str>> {
+        let bytes = self.input.as_bytes();
+        let start = self.pos;
+        let close = match bytes.get(start)? {
+            b'"' => b'"',
+            b'\'' => b'\'',
+            b'(' => b')',
+            _ => return None,
+        };
+
+        let mut i = start + 1;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 1,
+                b'(' if close == b')' => return None,
+                b if b == close => {
+                    self.pos = i + 1;
+                    return Some(unescape(&self.input[start + 1..i]));
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        None
+    }
+
+    // 行内链接 ](...) 中 ( 开始的部分：(url "title")
+    pub fn parse_inline_link(&mut self) -> Option<(Cow<'a, str>, Option<Cow<'a, str>>)> {
+        let start = self.pos;
+        let result = self.try_parse_inline_link();
+        if result.is_none() {
+            self.pos = start;
+        }
+        result
+    }
+
+    fn try_parse_inline_link(&mut self) -> Option<(Cow<'a, str>, Option<Cow<'a, str>>)> {
+        if self.input.as_bytes().get(self.pos) != Some(&b'(') {
+            return None;
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+
+        // 允许空目标 []()
+        if self.input.as_bytes().get(self.pos) == Some(&b')') {
+            self.pos += 1;
+            return Some((Cow::Borrowed(""), None));
+        }
+
+        let url = self.parse_destination()?;
+
+        // 标题前必须有空白
+        let title = if self.skip_whitespace() {
+            self.parse_title()
+        } else {
+            None
+        };
+        self.skip_whitespace();
+
+        if self.input.as_bytes().get(self.pos) != Some(&b')') {
+            return None;
+        }
+        self.pos += 1;
+
+        Some((url, title))
+    }
+
+    // 在段落开头解析一个链接定义，成功时返回规范化的标签
+    pub fn parse_definition(&mut self) -> Option<(String, LinkDefinition)> {
+        let start = self.pos;
+        let result = self.try_parse_definition();
+        if result.is_none() {
+            self.pos = start;
+        }
+        result
+    }
+
+    fn try_parse_definition(&mut self) -> Option<(String, LinkDefinition)> {
+        self.skip_spaces();
+        let label = self.parse_label()?;
+        if self.input.as_bytes().get(self.pos) != Some(&b':') {
+            return None;
+        }
+        self.pos += 1;
+
+        self.skip_spaces_and_newline();
+        let url = self.parse_destination()?.into_owned();
+        let after_url = self.pos;
+
+        // 标题之前必须有空白，之后到行尾只能有空白
+        if self.skip_spaces_and_newline()
+            && let Some(title) = self.parse_title()
+            && self.skip_to_line_end()
+        {
+            let definition = LinkDefinition {
+                url,
+                title: Some(title.into_owned()),
+            };
+            return Some((normalize_label(label), definition));
+        }
+
+        // 不带标题
+        self.pos = after_url;
+        if !self.skip_to_line_end() {
+            return None;
+        }
+
+        Some((normalize_label(label), LinkDefinition { url, title: None }))
+    }
+
+    fn skip_spaces(&mut self) -> bool {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        while self.pos < bytes.len() && (bytes[self.pos] == b' ' || bytes[self.pos] == b'\t') {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    // 跳过空白，最多包含一个换行
+    fn skip_spaces_and_newline(&mut self) -> bool {
+        let start = self.pos;
+        self.skip_spaces();
+        if self.input.as_bytes().get(self.pos) == Some(&b'\n') {
+            self.pos += 1;
+            self.skip_spaces();
+        }
+        self.pos > start
+    }
+
+    // 行尾只剩空白时跳到下一行开头
+    fn skip_to_line_end(&mut self) -> bool {
+        self.skip_spaces();
+        match self.input.as_bytes().get(self.pos) {
+            None => true,
+            Some(b'\n') => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,168 +1,80 @@
-use std::borrow::Cow;
-
-use crate::{
-    ast::{Block, Inline, ListItem},
-    lexer::InlineParser,
-};
-
-pub struct ListParser<'a> {
-    lines: Vec<&'a str>,
-    pos: usize,
+// 列表项标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListMarker {
+    // 无序列表为 - * +，有序列表为 . )
+    pub delimiter: u8,
+    // 有序列表的编号
+    pub start: u32,
+    // 标记加后面空格的宽度，即内容相对标记的列
+    pub width: usize,
+    // 标记之后没有内容
+    pub is_empty: bool,
 }
 
-impl<'a> ListParser<'a> {
-    pub fn new(lines: &[&'a str]) -> Self {
-        ListParser {
-            lines: lines.to_vec(),
-            pos: 0,
-        }
+impl ListMarker {
+    pub fn is_ordered(&self) -> bool {
+        matches!(self.delimiter, b'.' | b')')
     }
+}
 
-    pub fn parse(&mut self) -> Vec<Block<'a>> {
-        let mut blocks = Vec::new();
-        while self.pos < self.lines.len() {
-            if let Some(block) = self.parse_list(0) {
-                blocks.push(block);
-            } else {
-                self.pos += 1;
-            }
-        }
-
-        blocks
-    }
+// 解析去掉缩进后的行开头的列表标记：- * + 或最多 9 位数字加 . )
+pub fn parse_list_marker(line: &str) -> Option<ListMarker> {
+    let bytes = line.as_bytes();
+    let first = *bytes.first()?;
 
-    fn parse_list(&mut self, min_indent: usize) -> Option<Block<'a>> {
-        if self.pos >= self.lines.len() {
+    let (delimiter, start, marker_len) = if matches!(first, b'-' | b'*' | b'+') {
+        (first, 1, 1)
+    } else {
+        let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        if !(1..=9).contains(&digits) {
             return None;
         }
 
-        // 1. 检查当前行是否是列表项，且缩进达标
-        let line = self.lines[self.pos];
-        let indent = self.count_indent(line);
-
-        // 缩进变小返回
-        if indent < min_indent {
+        let delimiter = *bytes.get(digits)?;
+        if delimiter != b'.' && delimiter != b')' {
             return None;
         }
-
-        let trimmed = line.trim();
-        let (is_ordered, _) = self.parse_list_marker(trimmed)?;
-
-        let mut items = Vec::new();
-
-        // 2. 循环解析同级的列表项
-        while self.pos < self.lines.len() {
-            let line = &self.lines[self.pos];
-            let indent = self.count_indent(line);
-
-            if indent < min_indent {
-                break;
-            }
-
-            let marker_res = self.parse_list_marker(line.trim());
-            match marker_res {
-                Some((ordered, start)) => {
-                    // 如果列表类型变了打断
-                    if ordered != is_ordered {
-                        break;
-                    }
-
-                    // === 解析列表项 (ListItem) ===
-                    self.pos += 1;
-
-                    // 1. 提取当前行的内容
-                    let content_text = line.trim()[start..].trim();
-                    let mut item_children = Vec::new();
-
-                    let mut paragraph_children: Vec<Inline> = Vec::new();
-
-                    let mut inline_parser = InlineParser::new(&content_text);
-                    paragraph_children.extend(inline_parser.parse());
-
-                    // 2. 贪婪解析属于该 Item 的后续行 (子列表或多行内容)
-                    // 子列表的缩进必须 > 当前 indent
-                    loop {
-                        if self.pos >= self.lines.len() {
-                            break;
-                        }
-                        let next_line = &self.lines[self.pos];
-                        let next_indent = self.count_indent(next_line);
-
-                        // 跳空行
-                        if next_line.trim().is_empty() {
-                            self.pos += 1;
-                            continue;
-                        }
-
-                        // 如果下一行缩进更深，尝试递归解析子列表
-                        if next_indent > indent {
-                            if let Some(sub_list) = self.parse_list(next_indent) {
-                                item_children.push(sub_list);
-                            } else {
-                                let line = &self.lines[self.pos].trim();
-                                let mut inline_parser = InlineParser::new(&line);
-                                paragraph_children.push(Inline::Text(Cow::from(" ")));
-                                paragraph_children.extend(inline_parser.parse());
-
-                                self.pos += 1;
-                            }
-                        } else {
-                            // 下一行缩进持平或变小，说明该 Item 结束
-                            break;
-                        }
-                    }
-
-                    item_children.push(Block::Paragraph {
-                        children: paragraph_children,
-                    });
-
-                    items.push(ListItem {
-                        children: item_children,
-                    });
-                }
-                None => {
-                    break;
-                }
-            }
-        }
-
-        Some(Block::List {
-            ordered: is_ordered,
-            items,
-        })
-    }
-
-    fn count_indent(&self, line: &str) -> usize {
-        line.chars().take_while(|c| *c == ' ').count()
+        (delimiter, line[..digits].parse().ok()?, digits + 1)
+    };
+
+    // 标记后必须是空格或行尾
+    let spaces = bytes[marker_len..]
+        .iter()
+        .take_while(|b| **b == b' ')
+        .count();
+    if marker_len + spaces < bytes.len() && spaces == 0 {
+        return None;
     }
 
-    fn parse_list_marker(&self, line: &str) -> Option<(bool, usize)> {
-        let bytes = line.as_bytes();
-        if bytes.is_empty() {
-            return None;
-        }
-
-        let c = bytes[0] as char;
-
-        if c == '-' || c == '*' || c == '+' {
-            if line.len() == 1 || (line.len() > 1 && bytes[1] as char == ' ') {
-                return Some((false, if line.len() > 1 { 2 } else { 1 }));
-            }
-        }
-
-        if c.is_numeric() {
-            let mut i = 1;
-            while i < line.len() && (bytes[i] as char).is_numeric() {
-                i += 1;
-            }
-
-            if i < line.len() && bytes[i] as char == '.' {
-                if i + 1 == line.len() || (i + 1 < line.len() && bytes[i + 1] as char == ' ') {
-                    return Some((true, i + 2));
-                }
-            }
-        }
+    // 标记后 1~4 个空格计入宽度；没有内容或空格更多（缩进代码）时只算一个
+    let is_empty = marker_len + spaces == bytes.len();
+    let width = if is_empty || spaces > 4 {
+        marker_len + 1
+    } else {
+        marker_len + spaces
+    };
+
+    Some(ListMarker {
+        delimiter,
+        start,
+        width,
+        is_empty,
+    })
+}
 
-        None
+// 任务列表标记：列表项内容开头的 [ ]、[x] 或 [X]，之后必须有空格和内容
+// 返回勾选状态和去掉标记后的内容
+pub fn parse_task_marker(content: &str) -> Option<(bool, &str)> {
+    let checked = match content.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+
+    let rest = content[3..].strip_prefix(' ')?.trim_start();
+    if rest.is_empty() {
+        return None;
     }
+
+    Some((checked, rest))
 }
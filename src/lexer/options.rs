@@ -0,0 +1,66 @@
+/// 语法扩展开关，`Default` 等同于 [`ParseOptions::gfm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// GFM 表格
+    pub tables: bool,
+    /// `~~删除线~~`
+    pub strikethrough: bool,
+    /// 行内原始 HTML，关闭时按普通文本转义输出
+    pub raw_html: bool,
+    /// GFM 扩展自动链接：文本中的 `www.`、`https://` 网址和邮箱地址
+    pub autolinks: bool,
+    /// GFM 任务列表：`- [ ]` 和 `- [x]`
+    pub task_lists: bool,
+    /// 脚注：`[^label]` 引用和 `[^label]:` 定义
+    pub footnotes: bool,
+    /// 清空 `javascript:`、`vbscript:`、`file:` 和非图片 `data:` 链接
+    pub safe_urls: bool,
+}
+
+impl ParseOptions {
+    /// 严格 CommonMark，关闭全部扩展
+    pub fn commonmark() -> Self {
+        ParseOptions {
+            tables: false,
+            strikethrough: false,
+            raw_html: true,
+            autolinks: false,
+            task_lists: false,
+            footnotes: false,
+            safe_urls: false,
+        }
+    }
+
+    /// GitHub Flavored Markdown
+    pub fn gfm() -> Self {
+        ParseOptions {
+            tables: true,
+            strikethrough: true,
+            raw_html: true,
+            autolinks: true,
+            task_lists: true,
+            footnotes: true,
+            safe_urls: false,
+        }
+    }
+
+    /// 开启全部扩展。目前的扩展都属于 GFM，因此与 [`ParseOptions::gfm`] 相同
+    pub fn all() -> Self {
+        Self::gfm()
+    }
+
+    /// 不信任的输入：GFM 扩展，但不允许原始 HTML 和可执行脚本的链接
+    pub fn safe() -> Self {
+        ParseOptions {
+            raw_html: false,
+            safe_urls: true,
+            ..Self::gfm()
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::gfm()
+    }
+}
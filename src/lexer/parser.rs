@@ -1,58 +1,194 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashSet};
 
 use crate::{
     ast::*,
-    lexer::{InlineParser, ListParser},
+    lexer::{
+        HtmlBlockEnd, InfoParser, InlineParser, LinkParser, ListMarker, ParseOptions, RefMap,
+        html_block_start, normalize_label, number_footnotes, parse_list_marker, parse_task_marker,
+    },
 };
 
 pub struct Parser<'a> {
     input: &'a str,
+    options: ParseOptions,
+    // 链接引用定义，块解析阶段收集
+    pub(crate) refs: RefMap,
+    // 脚注定义的标签，规范化后，块解析阶段收集
+    footnote_labels: HashSet<String>,
+    // 打开中的容器块，栈底是文档本身
+    containers: Vec<Container<'a>>,
+    // 打开中的叶子块，属于栈顶容器
+    leaf: Option<Leaf<'a>>,
+    // 任务列表标记 [ ] 在输入中的字节位置，按出现顺序
+    pub(crate) task_offsets: Vec<usize>,
+}
+
+// 代码围栏：字符、长度与开头缩进
+#[derive(Clone, Copy)]
+struct CodeFence {
+    marker: u8,
+    len: usize,
+    indent: usize,
+}
+
+enum ContainerKind<'a> {
+    Document,
+    BlockQuote,
+    // 列表只包含列表项，由列表项决定能否继续
+    List {
+        marker: ListMarker,
+        tight: bool,
+        items: Vec<ListItem<'a>>,
+    },
+    // content_indent：内容相对列表项起点的列；checked：任务列表项的勾选状态
+    Item {
+        content_indent: usize,
+        checked: Option<bool>,
+    },
+    // [^label]: 开始的脚注定义，续行缩进 4 列
+    Footnote {
+        label: &'a str,
+    },
+}
+
+struct Container<'a> {
+    kind: ContainerKind<'a>,
+    children: Vec<Block<'a>>,
+    // 最后处理的一行是空行，用于判断列表是否松散
+    last_blank: bool,
+}
+
+impl<'a> Container<'a> {
+    fn new(kind: ContainerKind<'a>) -> Self {
+        Container {
+            kind,
+            children: Vec::new(),
+            last_blank: false,
+        }
+    }
+}
+
+enum Leaf<'a> {
+    Paragraph(Vec<&'a str>),
+    FencedCode {
+        fence: CodeFence,
+        info: &'a str,
+        lines: Vec<&'a str>,
+    },
+    IndentedCode(Vec<&'a str>),
+    Html {
+        end: HtmlBlockEnd,
+        lines: Vec<&'a str>,
+    },
+    Table {
+        headers: Vec<Vec<Inline<'a>>>,
+        alignments: Vec<Alignment>,
+        rows: Vec<Vec<Vec<Inline<'a>>>>,
+    },
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
-        Parser { input }
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        Parser {
+            input,
+            options,
+            refs: RefMap::new(),
+            footnote_labels: HashSet::new(),
+            containers: Vec::new(),
+            leaf: None,
+            task_offsets: Vec::new(),
+        }
     }
 
+    // 两个阶段：先解析块结构并收集链接定义，再统一解析行内元素
     pub fn parse(&mut self) -> Vec<Block<'a>> {
-        self.parse_blocks()
+        let mut blocks = self.parse_unnumbered();
+        if !self.footnote_labels.is_empty() {
+            number_footnotes(&mut blocks);
+        }
+        blocks
+    }
+
+    // 不给脚注编号，脚注定义留在原位，块的顺序与原文一致
+    pub(crate) fn parse_unnumbered(&mut self) -> Vec<Block<'a>> {
+        let mut blocks = self.parse_blocks();
+        self.parse_inlines(&mut blocks);
+        blocks
     }
 
     pub fn preprocess(input: &str) -> String {
         input.replace("\r\n", "\n").replace("\t", "    ") + "\n"
     }
 
-    fn flush_paragraph(&self, blocks: &mut Vec<Block<'a>>, lines: &mut Vec<&'a str>) {
-        if lines.is_empty() {
-            return;
-        }
-
-        let content = lines.join("\n");
-
-        let mut inline_parser = InlineParser::new(&content);
-        let inlines = inline_parser.parse();
+    // 块解析阶段的行内内容只是一个未解析的 Text，由 parse_inlines 展开
+    fn pending_inlines(text: impl Into<Cow<'a, str>>) -> Vec<Inline<'a>> {
+        vec![Inline::Text(text.into())]
+    }
 
-        let owned_inlines = inlines.into_iter().map(|i| i.into_owned()).collect();
+    fn parse_inlines(&self, blocks: &mut [Block<'a>]) {
+        for block in blocks {
+            match block {
+                Block::Heading { children, .. } | Block::Paragraph { children } => {
+                    self.resolve_inlines(children);
+                }
+                Block::BlockQuote(children) | Block::FootnoteDefinition { children, .. } => {
+                    self.parse_inlines(children)
+                }
+                Block::List { items, .. } => {
+                    for item in items {
+                        self.parse_inlines(&mut item.children);
+                    }
+                }
+                Block::Table { headers, rows, .. } => {
+                    headers
+                        .iter_mut()
+                        .chain(rows.iter_mut().flatten())
+                        .for_each(|cell| self.resolve_inlines(cell));
+                }
+                Block::CodeBlock { .. } | Block::ThematicBreak | Block::HtmlBlock(_) => {}
+            }
+        }
+    }
 
-        blocks.push(Block::Paragraph {
-            children: owned_inlines,
-        });
+    fn resolve_inlines(&self, children: &mut Vec<Inline<'a>>) {
+        let raw = match children.as_mut_slice() {
+            [Inline::Text(raw)] => std::mem::take(raw),
+            _ => return,
+        };
 
-        lines.clear();
+        let mut inline_parser = InlineParser::with_options(&raw, self.options)
+            .with_refs(&self.refs)
+            .with_footnotes(&self.footnote_labels);
+        *children = inline_parser
+            .parse()
+            .into_iter()
+            .map(|i| i.into_owned())
+            .collect();
     }
 
-    fn flush_blockquote(&self, blocks: &mut Vec<Block<'a>>, lines: &mut Vec<&'a str>) {
-        if lines.is_empty() {
-            return;
+    // 取出段落开头的链接定义，返回剩余内容的起始位置
+    fn take_definitions(&mut self, content: &str) -> usize {
+        let mut link_parser = LinkParser::new(content);
+        while let Some((label, definition)) = link_parser.parse_definition() {
+            // 同名定义以第一个为准
+            self.refs.entry(label).or_insert(definition);
         }
 
+        link_parser.pos()
+    }
+
+    fn paragraph_block(&mut self, lines: &[&'a str]) -> Option<Block<'a>> {
         let content = lines.join("\n");
-        let mut child_parser = Parser::new(&content);
-        let child_blocks = child_parser.parse();
-        let owned_blocks = child_blocks.into_iter().map(|b| b.into_owned()).collect();
-        blocks.push(Block::BlockQuote(owned_blocks));
+        let start = self.take_definitions(&content);
 
-        lines.clear();
+        let content = content[start..].trim_end();
+        (!content.is_empty()).then(|| Block::Paragraph {
+            children: Self::pending_inlines(content.to_string()),
+        })
     }
 
     fn parse_heading(&self, line: &'a str) -> Option<Block<'a>> {
@@ -75,15 +211,54 @@ impl<'a> Parser<'a> {
 
         let content_str = line[level..].trim();
 
-        let mut inline_parser = InlineParser::new(&content_str);
-        let inlines = inline_parser.parse();
-
         Some(Block::Heading {
             level: level as u8,
-            children: inlines,
+            children: Self::pending_inlines(content_str),
         })
     }
 
+    fn parse_setext_underline(&self, line: &str) -> Option<u8> {
+        if self.count_indent(line) > 3 {
+            return None;
+        }
+
+        let underline = line.trim();
+        let level = match underline.chars().next()? {
+            '=' => 1,
+            '-' => 2,
+            _ => return None,
+        };
+
+        let marker = if level == 1 { '=' } else { '-' };
+        if underline.chars().all(|c| c == marker) {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    // 把打开的段落变成 setext 标题；段落只剩链接定义时不构成标题，返回 false
+    fn close_setext_heading(&mut self, level: u8) -> bool {
+        let Some(Leaf::Paragraph(lines)) = self.leaf.take() else {
+            return false;
+        };
+
+        let content = lines.join("\n");
+        let start = self.take_definitions(&content);
+
+        let content = content[start..].trim();
+        if content.is_empty() {
+            return false;
+        }
+
+        self.add_block(Block::Heading {
+            level,
+            children: Self::pending_inlines(content.to_string()),
+        });
+
+        true
+    }
+
     fn is_thematic_break(&self, line: &str) -> bool {
         if line.len() < 3 {
             return false;
@@ -113,69 +288,81 @@ impl<'a> Parser<'a> {
         cnt >= 3
     }
 
-    fn count_indent(&self, line: &str) -> i32 {
-        let mut cnt = 0;
-        for c in line.chars() {
-            if c == ' ' {
-                cnt += 1;
-            } else {
-                break;
-            }
+    // 开头围栏：最多 3 格缩进，至少 3 个 ` 或 ~，反引号围栏的 info 不能含 `
+    fn parse_code_fence(&self, line: &'a str) -> Option<(CodeFence, &'a str)> {
+        let indent = self.count_indent(line);
+        if indent > 3 {
+            return None;
         }
 
-        cnt
-    }
-
-    fn parse_list_marker(&self, line: &str) -> Option<(bool, usize)> {
-        let bytes = line.as_bytes();
-        if bytes.is_empty() {
+        let rest = &line[indent..];
+        let marker = *rest.as_bytes().first()?;
+        if marker != b'`' && marker != b'~' {
             return None;
         }
 
-        let c = bytes[0] as char;
+        let len = rest.bytes().take_while(|b| *b == marker).count();
+        if len < 3 {
+            return None;
+        }
 
-        if c == '-' || c == '*' || c == '+' {
-            if line.len() == 1 || (line.len() > 1 && bytes[1] as char == ' ') {
-                return Some((false, if line.len() > 1 { 2 } else { 1 }));
-            }
+        let info = rest[len..].trim();
+        if marker == b'`' && info.contains('`') {
+            return None;
         }
 
-        if c.is_numeric() {
-            let mut i = 1;
-            while i < line.len() && (bytes[i] as char).is_numeric() {
-                i += 1;
-            }
+        Some((
+            CodeFence {
+                marker,
+                len,
+                indent,
+            },
+            info,
+        ))
+    }
 
-            if i < line.len() && bytes[i] as char == '.' {
-                if i + 1 == line.len() || (i + 1 < line.len() && bytes[i + 1] as char == ' ') {
-                    return Some((true, i + 2));
-                }
-            }
-        }
+    fn fenced_code_block(&self, lines: &[&'a str], info_string: &'a str) -> Block<'a> {
+        let (language, info) = InfoParser::new(info_string).parse();
 
-        None
+        Block::CodeBlock {
+            code: Cow::Owned(lines.join("\n")),
+            language,
+            info,
+        }
     }
 
-    fn flush_list_block(&self, blocks: &mut Vec<Block<'a>>, lines: &mut Vec<&'a str>) {
-        if lines.is_empty() {
-            return;
+    // 结束围栏：同一字符且长度不小于开头，之后只能有空白
+    fn is_closing_fence(&self, line: &str, fence: CodeFence) -> bool {
+        let indent = self.count_indent(line);
+        if indent > 3 {
+            return false;
         }
 
-        let mut list_parser = ListParser::new(lines);
-        let list_blocks = list_parser.parse();
-        blocks.extend(list_blocks);
+        let rest = &line[indent..];
+        let len = rest.bytes().take_while(|b| *b == fence.marker).count();
+
+        len >= fence.len && rest[len..].trim().is_empty()
+    }
 
-        lines.clear();
+    fn count_indent(&self, line: &str) -> usize {
+        line.bytes().take_while(|b| *b == b' ').count()
     }
 
     fn is_table_delimiter(&self, line: &'a str) -> bool {
         let trimmed_line = line.trim();
-        if !trimmed_line.contains('-') {
+        let cells = self.split_table_row(trimmed_line);
+
+        // 单列且不含 | 的 --- 留给 setext 标题和分割线
+        if cells.len() == 1 && !trimmed_line.contains('|') {
             return false;
         }
-        trimmed_line
-            .chars()
-            .all(|c| c == '|' || c == '-' || c == ':' || c == ' ')
+
+        cells.iter().all(|cell| {
+            let cell = cell.trim();
+            let cell = cell.strip_prefix(':').unwrap_or(cell);
+            let cell = cell.strip_suffix(':').unwrap_or(cell);
+            !cell.is_empty() && cell.chars().all(|c| c == '-')
+        })
     }
 
     fn parse_table_alignments(&self, line: &'a str) -> Vec<Alignment> {
@@ -203,17 +390,8 @@ impl<'a> Parser<'a> {
 
     fn split_table_row(&self, line: &'a str) -> Vec<&'a str> {
         let trimmed_line = line.trim();
-        let content = if trimmed_line.starts_with('|') {
-            &trimmed_line[1..]
-        } else {
-            trimmed_line
-        };
-
-        let content = if content.ends_with('|') {
-            &content[..content.len() - 1]
-        } else {
-            content
-        };
+        let content = trimmed_line.strip_prefix('|').unwrap_or(trimmed_line);
+        let content = content.strip_suffix('|').unwrap_or(content);
 
         content.split('|').collect()
     }
@@ -222,228 +400,526 @@ impl<'a> Parser<'a> {
         let parts = self.split_table_row(line);
         parts
             .into_iter()
-            .map(|part| {
-                let content = part.trim();
-                let mut parser = InlineParser::new(content);
-                parser.parse()
-            })
+            .map(|part| Self::pending_inlines(part.trim()))
             .collect()
     }
 
-    fn parse_blocks(&mut self) -> Vec<Block<'a>> {
-        let lines: Vec<&'a str> = self.input.lines().collect();
-        let mut idx = 0;
+    // 逐行处理：先匹配打开的容器，再尝试开始新块，剩余内容交给叶子块
+    pub(crate) fn parse_blocks(&mut self) -> Vec<Block<'a>> {
+        self.containers = vec![Container::new(ContainerKind::Document)];
+        self.leaf = None;
+        self.task_offsets.clear();
 
-        let mut blocks = Vec::new();
-        let mut cur_paragraph_lines: Vec<&'a str> = Vec::new();
+        for line in self.input.lines() {
+            self.process_line(line);
+        }
 
-        // 引用块缓存
-        let mut cur_quoto_lines: Vec<&'a str> = Vec::new();
+        self.close_containers(1);
+        self.close_leaf();
 
-        // codeblock
-        let mut in_code_block = false;
-        let mut cur_code_lines: Vec<&'a str> = Vec::new();
-        let mut language = "";
+        self.containers
+            .pop()
+            .map(|document| document.children)
+            .unwrap_or_default()
+    }
 
-        // list
-        let mut cur_list_lines: Vec<&'a str> = Vec::new();
+    fn process_line(&mut self, line: &'a str) {
+        let (mut rest, mut matched) = self.match_containers(line);
 
-        while idx < lines.len() {
-            let line = lines[idx];
-            let trimmed_line = line.trim();
+        // 代码、HTML 块的续行只在所有容器都匹配时成立
+        if matched == self.containers.len() && self.continue_leaf(rest) {
+            return;
+        }
 
-            // === CodeBlock ===
-            if in_code_block {
-                if trimmed_line.starts_with("```") {
-                    blocks.push(Block::CodeBlock {
-                        code: Cow::Owned(cur_code_lines.join("\n")),
-                        language: Cow::Borrowed(language),
-                    });
-                    in_code_block = false;
-                    cur_code_lines.clear();
-                    language = "";
-                } else {
-                    cur_code_lines.push(line);
+        // 本行是否开始了新容器
+        let mut opened = false;
+
+        loop {
+            let indent = self.count_indent(rest);
+            let is_blank = rest.trim().is_empty();
+            let in_paragraph = matches!(self.leaf, Some(Leaf::Paragraph(_)));
+            // 段落续行：段落所在的容器全部匹配
+            let continues_paragraph = in_paragraph && matched == self.containers.len();
+
+            // === Indented CodeBlock ===
+            // 缩进代码块不能打断段落
+            if indent >= 4 {
+                if !in_paragraph && !is_blank {
+                    self.close_containers(matched);
+                    self.open_leaf(Leaf::IndentedCode(vec![&rest[4..]]));
+                    return;
                 }
+                break;
+            }
+
+            let trimmed = &rest[indent..];
 
-                idx += 1;
+            // === BlockQuote ===
+            if trimmed.starts_with('>') {
+                self.close_containers(matched);
+                self.open_container(ContainerKind::BlockQuote);
+                matched = self.containers.len();
+                opened = true;
+
+                rest = Self::strip_quote_marker(trimmed);
                 continue;
             }
 
+            // === Heading ===
+            if let Some(heading) = self.parse_heading(trimmed) {
+                self.close_containers(matched);
+                self.close_leaf();
+                self.add_block(heading);
+                return;
+            }
+
             // 开始CodeBlock
-            if trimmed_line.starts_with("```") {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-                if !cur_quoto_lines.is_empty() {
-                    self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
-                }
-                if !cur_list_lines.is_empty() {
-                    self.flush_list_block(&mut blocks, &mut cur_list_lines);
+            if let Some((fence, info)) = self.parse_code_fence(rest) {
+                self.close_containers(matched);
+                self.open_leaf(Leaf::FencedCode {
+                    fence,
+                    info,
+                    lines: Vec::new(),
+                });
+                return;
+            }
+
+            // === HtmlBlock ===
+            // 第 7 类（单独一行的任意标签）不能打断段落
+            if self.options.raw_html
+                && let Some((end, can_interrupt)) = html_block_start(trimmed)
+                && (can_interrupt || !continues_paragraph)
+            {
+                self.close_containers(matched);
+                self.open_leaf(Leaf::Html {
+                    end,
+                    lines: vec![rest],
+                });
+                if end != HtmlBlockEnd::BlankLine && end.is_end(rest) {
+                    self.close_leaf();
                 }
+                return;
+            }
 
-                in_code_block = true;
-                language = trimmed_line.strip_prefix("```").unwrap_or("").trim();
+            // === Setext Heading ===
+            // 段落后的 === / --- 下划线优先于分割线和表格分隔行
+            if continues_paragraph
+                && let Some(level) = self.parse_setext_underline(rest)
+                && self.close_setext_heading(level)
+            {
+                return;
+            }
 
-                idx += 1;
+            // === Table ===
+            // 分隔行把段落的最后一行变成表头
+            if self.options.tables
+                && continues_paragraph
+                && self.is_table_delimiter(trimmed)
+                && self.open_table(trimmed)
+            {
+                return;
+            }
+
+            // === ThematicBreak ===
+            if self.is_thematic_break(trimmed) {
+                self.close_containers(matched);
+                self.close_leaf();
+                self.add_block(Block::ThematicBreak);
+                return;
+            }
+
+            // === Footnote ===
+            if self.options.footnotes
+                && let Some((label, content)) = Self::parse_footnote_start(trimmed)
+            {
+                self.close_containers(matched);
+                self.footnote_labels.insert(normalize_label(label));
+                self.open_container(ContainerKind::Footnote { label });
+                matched = self.containers.len();
+                opened = true;
+
+                rest = content;
                 continue;
             }
 
-            // === Table ===
-            if trimmed_line.contains('|') && idx + 1 < lines.len() {
-                let next_line = lines[idx + 1].trim();
+            // === List ===
+            // 打断段落的列表项不能为空，有序列表还必须从 1 开始
+            if let Some(marker) = parse_list_marker(trimmed)
+                && (!continues_paragraph
+                    || (!marker.is_empty && (!marker.is_ordered() || marker.start == 1)))
+            {
+                rest = if marker.is_empty {
+                    ""
+                } else {
+                    &trimmed[marker.width..]
+                };
 
-                if self.is_table_delimiter(next_line) {
-                    self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-                    if !cur_quoto_lines.is_empty() {
-                        self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
-                    }
-                    if !cur_list_lines.is_empty() {
-                        self.flush_list_block(&mut blocks, &mut cur_list_lines);
-                    }
+                // === Task List ===
+                let mut checked = None;
+                if self.options.task_lists
+                    && let Some((state, content)) = parse_task_marker(rest)
+                {
+                    checked = Some(state);
+                    // rest 是 input 的切片，地址之差就是标记的位置
+                    self.task_offsets
+                        .push(rest.as_ptr() as usize - self.input.as_ptr() as usize);
+                    rest = content;
+                }
 
-                    let headers = self.parse_table_row(trimmed_line);
-                    let alignments = self.parse_table_alignments(next_line);
+                self.close_containers(matched);
+                self.open_item(marker, indent + marker.width, checked);
+                matched = self.containers.len();
+                opened = true;
+                continue;
+            }
 
-                    let mut rows = Vec::new();
-                    idx += 2;
+            break;
+        }
 
-                    while idx < lines.len() {
-                        let row_line = lines[idx].trim();
+        // 懒惰续行：容器没有全部匹配，但没有开始新块的非空行仍属于打开的段落
+        if matched < self.containers.len()
+            && !rest.trim().is_empty()
+            && let Some(Leaf::Paragraph(lines)) = &mut self.leaf
+        {
+            lines.push(rest.trim_start());
+            return;
+        }
 
-                        if !row_line.contains('|') || row_line.is_empty() {
-                            break;
-                        }
+        self.close_containers(matched);
 
-                        rows.push(self.parse_table_row(row_line));
-                        idx += 1;
-                    }
+        // === Empty ===
+        if rest.trim().is_empty() {
+            if matches!(self.leaf, Some(Leaf::Paragraph(_) | Leaf::Table { .. })) {
+                self.close_leaf();
+            }
+            // 以空行开始的列表项不算空行
+            if !opened {
+                self.top_mut().last_blank = true;
+            }
+            return;
+        }
 
-                    blocks.push(Block::Table {
-                        headers,
-                        rows,
-                        alignments,
-                    });
-                    continue;
+        match &mut self.leaf {
+            // === Paragraph ===
+            // 保留行尾空格，硬换行由行内解析识别
+            Some(Leaf::Paragraph(lines)) => lines.push(rest.trim_start()),
+            Some(Leaf::Table { .. }) if rest.contains('|') => {
+                let row = self.parse_table_row(rest.trim());
+                if let Some(Leaf::Table { rows, .. }) = &mut self.leaf {
+                    rows.push(row);
                 }
             }
+            _ => {
+                self.close_leaf();
+                self.open_leaf(Leaf::Paragraph(vec![rest.trim_start()]));
+            }
+        }
+    }
 
-            // === ThematicBreak ===
-            if self.is_thematic_break(line) {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-
-                if !cur_quoto_lines.is_empty() {
-                    self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
+    // 依次匹配打开的容器，返回去掉容器标记后的剩余内容和匹配的容器数
+    fn match_containers(&self, line: &'a str) -> (&'a str, usize) {
+        let mut rest = line;
+        let mut matched = 1;
+        // 只在去掉引用标记后重新判断，避免深层嵌套时反复扫描整行
+        let mut is_blank = rest.trim().is_empty();
+
+        while let Some(container) = self.containers.get(matched) {
+            match container.kind {
+                ContainerKind::Document | ContainerKind::List { .. } => {}
+                ContainerKind::BlockQuote => {
+                    let indent = rest.bytes().take(4).take_while(|b| *b == b' ').count();
+                    if indent > 3 || !rest[indent..].starts_with('>') {
+                        break;
+                    }
+                    rest = Self::strip_quote_marker(&rest[indent..]);
+                    is_blank = rest.trim().is_empty();
                 }
-                if !cur_list_lines.is_empty() {
-                    self.flush_list_block(&mut blocks, &mut cur_list_lines);
+                ContainerKind::Item { content_indent, .. } => {
+                    if is_blank {
+                        // 列表项最多以一个空行开始，空列表项之后的空行结束该项
+                        let has_content = !container.children.is_empty()
+                            || matched + 1 < self.containers.len()
+                            || self.leaf.is_some();
+                        if !has_content {
+                            break;
+                        }
+                        rest = rest.get(content_indent..).unwrap_or("");
+                    } else if rest
+                        .bytes()
+                        .take(content_indent)
+                        .take_while(|b| *b == b' ')
+                        .count()
+                        == content_indent
+                    {
+                        rest = &rest[content_indent..];
+                    } else {
+                        break;
+                    }
+                }
+                // 空行不结束脚注定义
+                ContainerKind::Footnote { .. } => {
+                    if is_blank {
+                        rest = rest.get(4..).unwrap_or("");
+                    } else if rest.bytes().take(4).take_while(|b| *b == b' ').count() == 4 {
+                        rest = &rest[4..];
+                    } else {
+                        break;
+                    }
                 }
-
-                blocks.push(Block::ThematicBreak);
-                idx += 1;
-                continue;
             }
+            matched += 1;
+        }
 
-            // === List ===
-            // 正在收集列表块
-            if !cur_list_lines.is_empty() {
-                let is_marker = self.parse_list_marker(trimmed_line).is_some();
-                // 使用原行计算缩进
-                let indent = self.count_indent(line);
-                let is_indented = indent >= 2;
-
-                // if self.is_thematic_break(trimmed) {
-                //     self.flush_list_block(&mut blocks, &mut cur_list_lines);
-                //     blocks.push(Block::ThematicBreak);
-                //     continue;
-                // }
-
-                if trimmed_line.starts_with('#')
-                    || trimmed_line.starts_with('>')
-                    || trimmed_line.starts_with('`')
-                {
-                    self.flush_list_block(&mut blocks, &mut cur_list_lines);
-                } else if trimmed_line.is_empty() {
-                    cur_list_lines.push(line);
+        (rest, matched)
+    }
 
-                    idx += 1;
-                    continue;
+    // 把行加入围栏代码、缩进代码或 HTML 块，返回该行是否已被消耗
+    fn continue_leaf(&mut self, line: &'a str) -> bool {
+        let indent = self.count_indent(line);
+        let is_blank = line.trim().is_empty();
+
+        if let Some(Leaf::FencedCode { fence, .. }) = self.leaf
+            && self.is_closing_fence(line, fence)
+        {
+            self.close_leaf();
+            return true;
+        }
+
+        match &mut self.leaf {
+            Some(Leaf::FencedCode { fence, lines, .. }) => {
+                // 去掉不超过开头围栏缩进的空格
+                lines.push(&line[indent.min(fence.indent)..]);
+                true
+            }
+            Some(Leaf::Html { end, lines }) => {
+                // 空行结束的块不包含空行本身
+                if *end == HtmlBlockEnd::BlankLine && end.is_end(line) {
+                    self.close_leaf();
+                    return false;
                 }
-                // 如果是列表标记有缩进，继续收集
-                else if is_marker || is_indented {
-                    cur_list_lines.push(line);
 
-                    idx += 1;
-                    continue;
-                } else {
-                    self.flush_list_block(&mut blocks, &mut cur_list_lines);
+                lines.push(line);
+                if end.is_end(line) {
+                    self.close_leaf();
                 }
+                true
             }
+            Some(Leaf::IndentedCode(lines)) if is_blank || indent >= 4 => {
+                // 块内空行保留 4 列之后的内容
+                lines.push(line.get(4..).unwrap_or(""));
+                self.top_mut().last_blank = is_blank;
+                true
+            }
+            _ => false,
+        }
+    }
 
-            if self.parse_list_marker(trimmed_line).is_some() {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-                if !cur_quoto_lines.is_empty() {
-                    self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
-                }
+    // [^label]: 之后是定义的第一行内容，标签不能含空白和方括号
+    fn parse_footnote_start(line: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = line.strip_prefix("[^")?;
+        let end = rest.find("]:")?;
+        let label = &rest[..end];
+        if label.is_empty() || label.contains(|c: char| c.is_whitespace() || c == '[' || c == ']') {
+            return None;
+        }
 
-                // if self.is_thematic_break(trimmed) {
-                //     blocks.push(Block::ThematicBreak);
-                //     continue;
-                // }
+        Some((label, rest[end + 2..].trim_start()))
+    }
 
-                cur_list_lines.push(line);
+    fn strip_quote_marker(line: &'a str) -> &'a str {
+        let rest = &line[1..];
+        rest.strip_prefix(' ').unwrap_or(rest)
+    }
 
-                idx += 1;
-                continue;
-            }
+    fn top_mut(&mut self) -> &mut Container<'a> {
+        self.containers
+            .last_mut()
+            .expect("文档容器在解析期间一直打开")
+    }
 
-            // === BlockQuote ===
-            if trimmed_line.starts_with('>') {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-                let content = trimmed_line.strip_prefix('>').unwrap_or("").trim_start();
-                cur_quoto_lines.push(content);
+    // 在栈顶容器中开始一个新的子块
+    // 列表只能包含列表项，其他块会先结束列表；子块之前有空行时列表是松散的
+    fn prepare_child(&mut self, is_item: bool) {
+        while !is_item && matches!(self.top_mut().kind, ContainerKind::List { .. }) {
+            self.close_top();
+        }
 
-                idx += 1;
-                continue;
-            }
+        let depth = self.containers.len();
+        let top = &mut self.containers[depth - 1];
+        if !std::mem::take(&mut top.last_blank) {
+            return;
+        }
 
-            if !cur_quoto_lines.is_empty() {
-                self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
-            }
+        let list = match &top.kind {
+            ContainerKind::List { items, .. } if !items.is_empty() => depth - 1,
+            ContainerKind::Item { .. } if !top.children.is_empty() => depth - 2,
+            _ => return,
+        };
+        if let ContainerKind::List { tight, .. } = &mut self.containers[list].kind {
+            *tight = false;
+        }
+    }
 
-            // === Empty ===
-            if trimmed_line.is_empty() {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
+    fn add_block(&mut self, block: Block<'a>) {
+        self.prepare_child(false);
+        self.top_mut().children.push(block);
+    }
 
-                idx += 1;
-                continue;
-            }
+    fn open_leaf(&mut self, leaf: Leaf<'a>) {
+        self.close_leaf();
+        self.prepare_child(false);
+        self.leaf = Some(leaf);
+    }
 
-            // === Heading ===
-            if let Some(heading) = self.parse_heading(trimmed_line) {
-                self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-                blocks.push(heading);
+    fn open_container(&mut self, kind: ContainerKind<'a>) {
+        self.close_leaf();
+        self.prepare_child(matches!(kind, ContainerKind::Item { .. }));
+        self.containers.push(Container::new(kind));
+    }
 
-                idx += 1;
-                continue;
-            }
+    // 标记与当前列表不同时开始新列表
+    fn open_item(&mut self, marker: ListMarker, content_indent: usize, checked: Option<bool>) {
+        self.close_leaf();
+
+        let same_list = matches!(
+            &self.top_mut().kind,
+            ContainerKind::List { marker: current, .. } if current.delimiter == marker.delimiter
+        );
+        if !same_list {
+            self.open_container(ContainerKind::List {
+                marker,
+                tight: true,
+                items: Vec::new(),
+            });
+        }
 
-            // === Paragraph ===
-            cur_paragraph_lines.push(trimmed_line);
-            idx += 1;
+        self.open_container(ContainerKind::Item {
+            content_indent,
+            checked,
+        });
+    }
+
+    // 段落最后一行与分隔行列数相同时成为表头，之前的行仍是段落
+    fn open_table(&mut self, delimiter: &'a str) -> bool {
+        let Some(Leaf::Paragraph(lines)) = &mut self.leaf else {
+            return false;
+        };
+        let Some(header) = lines.last().map(|line| line.trim()) else {
+            return false;
+        };
+
+        if !header.contains('|')
+            || self.split_table_row(header).len() != self.split_table_row(delimiter).len()
+        {
+            return false;
         }
 
-        // 处理缓存
-        self.flush_paragraph(&mut blocks, &mut cur_paragraph_lines);
-        self.flush_blockquote(&mut blocks, &mut cur_quoto_lines);
+        if let Some(Leaf::Paragraph(lines)) = &mut self.leaf {
+            lines.pop();
+        }
+        let headers = self.parse_table_row(header);
+        let alignments = self.parse_table_alignments(delimiter);
 
-        if in_code_block {
-            blocks.push(Block::CodeBlock {
-                code: Cow::Owned(cur_code_lines.join("\n")),
-                language: Cow::Borrowed(language),
-            });
+        self.open_leaf(Leaf::Table {
+            headers,
+            alignments,
+            rows: Vec::new(),
+        });
+        true
+    }
+
+    fn close_leaf(&mut self) {
+        let Some(leaf) = self.leaf.take() else {
+            return;
+        };
+
+        let block = match leaf {
+            Leaf::Paragraph(lines) => self.paragraph_block(&lines),
+            Leaf::FencedCode { info, lines, .. } => Some(self.fenced_code_block(&lines, info)),
+            Leaf::IndentedCode(mut lines) => {
+                while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                    lines.pop();
+                }
+
+                Some(Block::CodeBlock {
+                    code: Cow::Owned(lines.join("\n")),
+                    language: Cow::Borrowed(""),
+                    info: CodeInfo::default(),
+                })
+            }
+            Leaf::Html { mut lines, .. } => {
+                while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                    lines.pop();
+                }
+
+                Some(Block::HtmlBlock(Cow::Owned(lines.join("\n"))))
+            }
+            Leaf::Table {
+                headers,
+                alignments,
+                rows,
+            } => Some(Block::Table {
+                headers,
+                rows,
+                alignments,
+            }),
+        };
+
+        if let Some(block) = block {
+            self.top_mut().children.push(block);
         }
+    }
 
-        self.flush_list_block(&mut blocks, &mut cur_list_lines);
+    fn close_containers(&mut self, len: usize) {
+        while self.containers.len() > len.max(1) {
+            self.close_top();
+        }
+    }
 
-        blocks
+    // 结束栈顶容器并放入上一层；列表和列表项末尾的空行向外传递
+    fn close_top(&mut self) {
+        self.close_leaf();
+
+        let Some(container) = self.containers.pop() else {
+            return;
+        };
+        let parent = self.top_mut();
+
+        match container.kind {
+            ContainerKind::Document => unreachable!("文档容器不会被提前结束"),
+            ContainerKind::BlockQuote => {
+                parent.children.push(Block::BlockQuote(container.children));
+            }
+            ContainerKind::List {
+                marker,
+                tight,
+                items,
+            } => {
+                parent.children.push(Block::List {
+                    ordered: marker.is_ordered(),
+                    start: marker.start,
+                    tight,
+                    items,
+                });
+                parent.last_blank |= container.last_blank;
+            }
+            ContainerKind::Item { checked, .. } => {
+                if let ContainerKind::List { items, .. } = &mut parent.kind {
+                    items.push(ListItem {
+                        children: container.children,
+                        checked,
+                    });
+                }
+                parent.last_blank |= container.last_blank;
+            }
+            ContainerKind::Footnote { label } => {
+                parent.children.push(Block::FootnoteDefinition {
+                    label: Cow::Borrowed(label),
+                    number: 0,
+                    references: 0,
+                    children: container.children,
+                });
+            }
+        }
     }
 }
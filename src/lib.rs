@@ -0,0 +1,27 @@
+pub mod ast;
+pub mod lexer;
+pub mod renderer;
+pub mod tasks;
+
+pub use ast::{Alignment, Block, CodeInfo, Inline, LineRange, ListItem};
+pub use lexer::{ParseOptions, Parser};
+pub use renderer::{HtmlRenderer, RenderOptions, SoftBreakStyle};
+pub use tasks::{Task, find_tasks, toggle_task, toggle_task_by_text};
+
+/// 一次性完成预处理、解析与渲染，返回 HTML 片段
+pub fn to_html(input: &str) -> String {
+    to_html_with_options(input, &ParseOptions::default(), &RenderOptions::default())
+}
+
+/// 同 [`to_html`]，按 `options` 开关语法扩展，按 `render_options` 控制输出
+pub fn to_html_with_options(
+    input: &str,
+    options: &ParseOptions,
+    render_options: &RenderOptions,
+) -> String {
+    let input = Parser::preprocess(input);
+    let mut parser = Parser::with_options(&input, *options);
+    let blocks = parser.parse();
+
+    HtmlRenderer::render_with_options(&blocks, render_options)
+}
@@ -1,84 +1,364 @@
-mod ast;
-mod lexer;
-mod renderer;
+use std::{
+    collections::HashSet,
+    env, fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
 
-use std::{env, fs, time::Instant};
+use markdown::{HtmlRenderer, ParseOptions, Parser, RenderOptions, SoftBreakStyle};
 
-use lexer::Parser;
+const USAGE: &str = "\
+Usage: markdown [OPTIONS] [INPUT]...
 
-use crate::renderer::HtmlRenderer;
+Convert Markdown to HTML. Reads stdin when no INPUT (or `-`) is given.
 
-fn performace_test() {
-    let loop_time = 5;
-    let mut costs: Vec<u128> = Vec::new();
+Options:
+  -o, --output <PATH>    Write to PATH instead of stdout; PATH is a directory
+                         when several inputs are given or it ends with `/`
+  -f, --format <FORMAT>  Output format: html (default) or ast
+  -s, --standalone       Wrap the output in a full HTML document
+      --title <TITLE>    Document title for --standalone (default: file name)
+  -p, --profile <NAME>   Syntax profile: gfm (default), commonmark, safe or all
+                         (currently the same as gfm)
+      --enable <EXT>     Enable an extension: tables, strikethrough, raw-html,
+                         autolinks, task-lists, footnotes, safe-urls
+      --disable <EXT>    Disable an extension
+      --soft-break <STYLE>
+                         Render line breaks inside paragraphs as newline
+                         (default), space or br
+      --bench <N>        Convert every input N times and report timings
+  -h, --help             Print this help";
 
-    let cwd = env::current_dir().unwrap();
-    let md_path = cwd.join("performance.md");
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Html,
+    Ast,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Ast => "txt",
+        }
+    }
+}
+
+struct Options {
+    inputs: Vec<String>,
+    output: Option<PathBuf>,
+    format: Format,
+    standalone: bool,
+    title: Option<String>,
+    bench: Option<usize>,
+    parse_options: ParseOptions,
+    render_options: RenderOptions,
+}
 
-    let md_bytes = fs::read(md_path).unwrap();
-    let md_string = String::from_utf8(md_bytes).unwrap();
+enum CliError {
+    Usage(String),
+    Io { path: String, source: io::Error },
+    Utf8 { path: String },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{msg}"),
+            CliError::Io { path, source } => write!(f, "{path}: {source}"),
+            CliError::Utf8 { path } => write!(f, "{path}: input is not valid UTF-8"),
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, CliError> {
+    let mut options = Options {
+        inputs: Vec::new(),
+        output: None,
+        format: Format::Html,
+        standalone: false,
+        title: None,
+        bench: None,
+        parse_options: ParseOptions::default(),
+        render_options: RenderOptions::default(),
+    };
 
-    let formated_md_string = Parser::preprocess(&md_string);
+    let mut only_inputs = false;
 
-    let mut parser = Parser::new(&formated_md_string);
+    while let Some(arg) = args.next() {
+        if only_inputs || arg == "-" || !arg.starts_with('-') {
+            options.inputs.push(arg);
+            continue;
+        }
 
-    for _ in 0..loop_time {
-        let start_time = Instant::now();
+        // 支持 --name=value 写法
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                (name.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
 
-        Parser::preprocess(&md_string);
-        HtmlRenderer::render(&parser.parse());
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| CliError::Usage(format!("missing value for `{name}`")))
+        };
 
-        let duration = start_time.elapsed();
-        costs.push(duration.as_nanos());
+        match name.as_str() {
+            "--" => only_inputs = true,
+            "-h" | "--help" => return Ok(None),
+            "-s" | "--standalone" => options.standalone = true,
+            "-o" | "--output" => options.output = Some(PathBuf::from(value(&name)?)),
+            "--title" => options.title = Some(value(&name)?),
+            "-f" | "--format" => {
+                options.format = match value(&name)?.as_str() {
+                    "html" => Format::Html,
+                    "ast" => Format::Ast,
+                    other => return Err(CliError::Usage(format!("unknown format `{other}`"))),
+                }
+            }
+            "-p" | "--profile" => {
+                options.parse_options = match value(&name)?.as_str() {
+                    "gfm" => ParseOptions::gfm(),
+                    "commonmark" => ParseOptions::commonmark(),
+                    "safe" => ParseOptions::safe(),
+                    "all" => ParseOptions::all(),
+                    other => return Err(CliError::Usage(format!("unknown profile `{other}`"))),
+                }
+            }
+            "--enable" | "--disable" => {
+                let ext = value(&name)?;
+                let flag = match ext.as_str() {
+                    "tables" => &mut options.parse_options.tables,
+                    "strikethrough" => &mut options.parse_options.strikethrough,
+                    "raw-html" => &mut options.parse_options.raw_html,
+                    "autolinks" => &mut options.parse_options.autolinks,
+                    "task-lists" => &mut options.parse_options.task_lists,
+                    "footnotes" => &mut options.parse_options.footnotes,
+                    "safe-urls" => &mut options.parse_options.safe_urls,
+                    _ => return Err(CliError::Usage(format!("unknown extension `{ext}`"))),
+                };
+                *flag = name == "--enable";
+            }
+            "--soft-break" => {
+                options.render_options.soft_break = match value(&name)?.as_str() {
+                    "newline" => SoftBreakStyle::Newline,
+                    "space" => SoftBreakStyle::Space,
+                    "br" => SoftBreakStyle::HardBreak,
+                    other => {
+                        return Err(CliError::Usage(format!(
+                            "unknown soft break style `{other}`"
+                        )));
+                    }
+                }
+            }
+            "--bench" => {
+                let n = value(&name)?;
+                options.bench = match n.parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(CliError::Usage(format!("invalid bench count `{n}`"))),
+                }
+            }
+            _ => return Err(CliError::Usage(format!("unknown option `{arg}`"))),
+        }
     }
 
-    let total_cost: u128 = costs.iter().sum();
-    println!("total cost: {}ms", total_cost as f64 / 1_000_000.0);
-    println!("================================");
-    costs
-        .iter()
-        .enumerate()
-        .for_each(|(i, c)| println!("loop {} cost: {:?}ms", i, *c as f64 / 1_000_000.0));
-    println!("================================");
-    println!(
-        "Average cost: {:?}ms",
-        total_cost as f64 / loop_time as f64 / 1_000_000.0
-    );
+    if options.inputs.is_empty() {
+        options.inputs.push("-".to_string());
+    }
+
+    if options.inputs.iter().filter(|i| *i == "-").count() > 1 {
+        return Err(CliError::Usage("stdin can only be read once".to_string()));
+    }
+
+    // 输出到目录时按输入的文件名命名，同名的输入会互相覆盖
+    if options.output.is_some() && options.bench.is_none() && options.inputs.len() > 1 {
+        let mut stems = HashSet::new();
+        if let Some(input) = options.inputs.iter().find(|i| !stems.insert(input_stem(i))) {
+            return Err(CliError::Usage(format!(
+                "`{input}` has the same output file name as an earlier input"
+            )));
+        }
+    }
+
+    Ok(Some(options))
 }
 
-fn output_test() {
-    let cwd = env::current_dir().unwrap();
-    let md_path = cwd.join("test.md");
-    let html_path = cwd.join("test.html");
+fn read_input(input: &str) -> Result<String, CliError> {
+    let (path, bytes) = if input == "-" {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|source| CliError::Io {
+                path: "<stdin>".to_string(),
+                source,
+            })?;
+        ("<stdin>", bytes)
+    } else {
+        let bytes = fs::read(input).map_err(|source| CliError::Io {
+            path: input.to_string(),
+            source,
+        })?;
+        (input, bytes)
+    };
 
-    let md_bytes = fs::read(md_path).unwrap();
-    let mut md_string = String::from_utf8(md_bytes).unwrap();
-    md_string = Parser::preprocess(&md_string);
+    String::from_utf8(bytes).map_err(|_| CliError::Utf8 {
+        path: path.to_string(),
+    })
+}
 
-    let mut p = Parser::new(&md_string);
-    let ast = p.parse();
+fn input_stem(input: &str) -> String {
+    if input == "-" {
+        return "stdin".to_string();
+    }
+
+    Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string())
+}
 
-    println!("{:#?}", ast);
+fn convert(options: &Options, input: &str, source: &str) -> String {
+    let formated_source = Parser::preprocess(source);
+    let ast = Parser::with_options(&formated_source, options.parse_options).parse();
 
-    let html = HtmlRenderer::render(&ast);
+    match options.format {
+        Format::Ast => format!("{:#?}\n", ast),
+        Format::Html => {
+            let html = HtmlRenderer::render_with_options(&ast, &options.render_options);
+            if !options.standalone {
+                return html;
+            }
 
-    let full_html = format!(
-        r#"<!DOCTYPE html>
-    <html>
-    <head><title>Rust Markdown Compiler</title></head>
-    <body>
+            let title = options.title.clone().unwrap_or_else(|| input_stem(input));
+            format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+                HtmlRenderer::escape_html(&title),
+                html
+            )
+        }
+    }
+}
 
-    {}
+fn bench(options: &Options, loop_time: usize) -> Result<(), CliError> {
+    for input in &options.inputs {
+        let source = read_input(input)?;
+        let mut costs: Vec<u128> = Vec::new();
 
-    </body>
-    </html>"#,
-        html
-    );
+        for _ in 0..loop_time {
+            let start_time = Instant::now();
 
-    fs::write(html_path, full_html).unwrap();
+            markdown::to_html_with_options(
+                &source,
+                &options.parse_options,
+                &options.render_options,
+            );
+
+            let duration = start_time.elapsed();
+            costs.push(duration.as_nanos());
+        }
+
+        let total_cost: u128 = costs.iter().sum();
+        eprintln!("{}", input);
+        eprintln!("total cost: {}ms", total_cost as f64 / 1_000_000.0);
+        eprintln!("================================");
+        costs
+            .iter()
+            .enumerate()
+            .for_each(|(i, c)| eprintln!("loop {} cost: {:?}ms", i, *c as f64 / 1_000_000.0));
+        eprintln!("================================");
+        eprintln!(
+            "Average cost: {:?}ms",
+            total_cost as f64 / loop_time as f64 / 1_000_000.0
+        );
+    }
+
+    Ok(())
+}
+
+fn write_output(path: &Path, content: &str) -> Result<(), CliError> {
+    fs::write(path, content).map_err(|source| CliError::Io {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+fn run(options: &Options) -> Vec<CliError> {
+    if let Some(loop_time) = options.bench {
+        return bench(options, loop_time).err().into_iter().collect();
+    }
+
+    // 多个输入或以 / 结尾时输出到目录
+    let output_dir = options.output.as_ref().filter(|path| {
+        options.inputs.len() > 1 || path.is_dir() || path.to_string_lossy().ends_with('/')
+    });
+
+    if let Some(dir) = output_dir
+        && let Err(source) = fs::create_dir_all(dir)
+    {
+        return vec![CliError::Io {
+            path: dir.display().to_string(),
+            source,
+        }];
+    }
+
+    let mut errors = Vec::new();
+    let mut stdout = io::stdout().lock();
+
+    for input in &options.inputs {
+        let result = read_input(input).and_then(|source| {
+            let output = convert(options, input, &source);
+
+            match (&options.output, output_dir) {
+                (_, Some(dir)) => {
+                    let file_name = format!("{}.{}", input_stem(input), options.format.extension());
+                    write_output(&dir.join(file_name), &output)
+                }
+                (Some(path), None) => write_output(path, &output),
+                (None, None) => {
+                    stdout
+                        .write_all(output.as_bytes())
+                        .map_err(|source| CliError::Io {
+                            path: "<stdout>".to_string(),
+                            source,
+                        })
+                }
+            }
+        });
+
+        if let Err(err) = result {
+            errors.push(err);
+        }
+    }
+
+    errors
 }
 
-fn main() {
-    output_test();
-    performace_test();
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let errors = run(&options);
+    for err in &errors {
+        eprintln!("error: {err}");
+    }
+
+    if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
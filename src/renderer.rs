@@ -1,53 +1,109 @@
 use crate::ast::*;
 
-pub struct HtmlRenderer;
+/// 段落内软换行的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SoftBreakStyle {
+    /// 原样输出换行
+    #[default]
+    Newline,
+    /// 输出一个空格
+    Space,
+    /// 输出 `<br />`，适合聊天消息这类回车即换行的场景
+    HardBreak,
+}
+
+/// 渲染选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    pub soft_break: SoftBreakStyle,
+}
+
+pub struct HtmlRenderer {
+    options: RenderOptions,
+}
 
 impl HtmlRenderer {
     pub fn render(blocks: &[Block]) -> String {
-        let mut output = String::new();
-
-        for block in blocks {
-            output.push_str(&Self::render_block(block));
-            output.push('\n');
-        }
+        Self::render_with_options(blocks, &RenderOptions::default())
+    }
 
-        output
+    pub fn render_with_options(blocks: &[Block], options: &RenderOptions) -> String {
+        let renderer = HtmlRenderer { options: *options };
+        renderer.render_blocks(blocks)
     }
 
-    fn render_block(block: &Block) -> String {
+    fn render_block(&self, block: &Block) -> String {
         match block {
             Block::Heading { level, children } => {
-                let content = Self::render_inlines(children);
+                let content = self.render_inlines(children);
                 format!("<h{level}>{content}</h{level}>")
             }
             Block::Paragraph { children } => {
-                let content = Self::render_inlines(children);
+                let content = self.render_inlines(children);
                 format!("<p>{content}</p>")
             }
             Block::BlockQuote(children) => {
-                let content = Self::render_blocks(children);
+                let content = self.render_blocks(children);
                 format!("<blockquote>\n{content}</blockquote>")
             }
-            Block::CodeBlock { code, language } => {
+            Block::CodeBlock {
+                code,
+                language,
+                info,
+            } => {
                 let safe_code = Self::escape_html(code);
                 let lang_class = if language.is_empty() {
                     String::new()
                 } else {
                     format!(" class=\"language-{}\"", Self::escape_html(language))
                 };
-                format!("<pre><code{}>{}</code></pre>", lang_class, safe_code)
+                let data_attrs = Self::render_code_info(info);
+                format!(
+                    "<pre><code{}{}>{}</code></pre>",
+                    lang_class, data_attrs, safe_code
+                )
             }
             Block::ThematicBreak => "<hr />".to_string(),
-            Block::List { ordered, items } => {
+            Block::HtmlBlock(html) => html.to_string(),
+            Block::List {
+                ordered,
+                start,
+                tight,
+                items,
+            } => {
                 let tag = if *ordered { "ol" } else { "ul" };
+                let start_attr = if *ordered && *start != 1 {
+                    format!(" start=\"{start}\"")
+                } else {
+                    String::new()
+                };
                 let mut content = String::new();
 
                 for item in items {
-                    let item_html = Self::render_blocks(&item.children);
-                    content.push_str(&format!("<li>{}</li>\n", item_html));
+                    let item_html = if *tight {
+                        self.render_tight_item(&item.children)
+                    } else {
+                        self.render_blocks(&item.children)
+                    };
+
+                    match item.checked {
+                        Some(checked) => {
+                            // 复选框放在第一个段落内
+                            let checkbox = Self::render_checkbox(checked);
+                            let item_html = match item_html.strip_prefix("<p>") {
+                                Some(rest) => format!("<p>{checkbox}{rest}"),
+                                None => format!("{checkbox}{item_html}"),
+                            };
+                            content.push_str(&format!(
+                                "<li class=\"task-list-item\">{}</li>\n",
+                                item_html
+                            ));
+                        }
+                        None => content.push_str(&format!("<li>{}</li>\n", item_html)),
+                    }
                 }
 
-                format!("<{}>\n{}</{}>", tag, content, tag)
+                format!("<{}{}>\n{}</{}>", tag, start_attr, content, tag)
             }
             Block::Table {
                 headers,
@@ -66,7 +122,7 @@ impl HtmlRenderer {
                         Alignment::Right => " style=\"text-align: right\"",
                         Alignment::None => "",
                     };
-                    let content = Self::render_inlines(header_cell);
+                    let content = self.render_inlines(header_cell);
                     html.push_str(&format!("<th{}>{}</th>\n", style, content));
                 }
                 html.push_str("</tr>\n</thead>\n");
@@ -83,7 +139,7 @@ impl HtmlRenderer {
                             Alignment::Right => " style=\"text-align: right\"",
                             Alignment::None => "",
                         };
-                        let content = Self::render_inlines(cell);
+                        let content = self.render_inlines(cell);
                         html.push_str(&format!("<td{}>{}</td>\n", style, content));
                     }
                     html.push_str("</tr>\n");
@@ -92,64 +148,243 @@ impl HtmlRenderer {
 
                 html
             }
+            Block::FootnoteDefinition {
+                label,
+                number,
+                references,
+                children,
+            } => {
+                let backrefs = (1..=*references)
+                    .map(|index| Self::render_backref(label, *number, index))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                // 返回链接放在最后一个段落内，没有段落时单独成段
+                let content = match children.split_last() {
+                    Some((Block::Paragraph { children: inlines }, rest)) => format!(
+                        "{}<p>{} {}</p>\n",
+                        self.render_blocks(rest),
+                        self.render_inlines(inlines),
+                        backrefs
+                    ),
+                    _ => format!("{}<p>{}</p>\n", self.render_blocks(children), backrefs),
+                };
+
+                format!(
+                    "<li id=\"fn-{}\">\n{}</li>",
+                    Self::escape_html(label),
+                    content
+                )
+            }
+        }
+    }
+
+    // info string 的其余部分输出为 data-* 属性
+    fn render_code_info(info: &CodeInfo) -> String {
+        let mut attrs = String::new();
+
+        for (key, value) in &info.attributes {
+            let key = key.to_ascii_lowercase();
+            let key = key.strip_prefix("data-").unwrap_or(&key);
+            let valid_key = key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_key {
+                continue;
+            }
+            attrs.push_str(&format!(" data-{}=\"{}\"", key, Self::escape_html(value)));
         }
+
+        if !info.classes.is_empty() {
+            let classes: Vec<&str> = info.classes.iter().map(|c| c.as_ref()).collect();
+            attrs.push_str(&format!(
+                " data-class=\"{}\"",
+                Self::escape_html(&classes.join(" "))
+            ));
+        }
+
+        if !info.highlight_lines.is_empty() {
+            let ranges: Vec<String> = info
+                .highlight_lines
+                .iter()
+                .map(|r| {
+                    if r.start == r.end {
+                        r.start.to_string()
+                    } else {
+                        format!("{}-{}", r.start, r.end)
+                    }
+                })
+                .collect();
+            attrs.push_str(&format!(" data-highlight=\"{}\"", ranges.join(",")));
+        }
+
+        attrs
     }
 
-    fn render_blocks(blocks: &[Block]) -> String {
+    fn render_blocks(&self, blocks: &[Block]) -> String {
         let mut output = String::new();
+        let is_footnote =
+            |block: Option<&Block>| matches!(block, Some(Block::FootnoteDefinition { .. }));
 
-        for block in blocks {
-            output.push_str(&Self::render_block(block));
+        for (i, block) in blocks.iter().enumerate() {
+            // 连续的脚注定义放进同一个脚注区
+            let footnote = is_footnote(Some(block));
+            if footnote && (i == 0 || !is_footnote(blocks.get(i - 1))) {
+                output.push_str("<section class=\"footnotes\" data-footnotes>\n<ol>\n");
+            }
+
+            output.push_str(&self.render_block(block));
             output.push('\n');
+
+            if footnote && !is_footnote(blocks.get(i + 1)) {
+                output.push_str("</ol>\n</section>\n");
+            }
+        }
+
+        output
+    }
+
+    // 第 index 次引用的返回链接，第二次起带上序号
+    fn render_backref(label: &str, number: usize, index: usize) -> String {
+        let href = Self::escape_html(&Self::encode_url(label));
+        let (suffix, sup) = if index == 1 {
+            (String::new(), String::new())
+        } else {
+            (
+                format!("-{index}"),
+                format!("<sup class=\"footnote-ref\">{index}</sup>"),
+            )
+        };
+
+        format!(
+            "<a href=\"#fnref-{href}{suffix}\" class=\"footnote-backref\" data-footnote-backref data-footnote-backref-idx=\"{number}{suffix}\" aria-label=\"Back to reference {number}{suffix}\">↩{sup}</a>"
+        )
+    }
+
+    fn render_checkbox(checked: bool) -> &'static str {
+        if checked {
+            "<input type=\"checkbox\" disabled=\"\" checked=\"\" /> "
+        } else {
+            "<input type=\"checkbox\" disabled=\"\" /> "
+        }
+    }
+
+    // 紧凑列表项：段落直接输出内容，其他块前后换行
+    fn render_tight_item(&self, blocks: &[Block]) -> String {
+        let mut output = String::new();
+
+        for block in blocks {
+            if let Block::Paragraph { children } = block {
+                output.push_str(&self.render_inlines(children));
+            } else {
+                if !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(&self.render_block(block));
+                output.push('\n');
+            }
         }
 
         output
     }
 
-    fn render_inlines(inlines: &[Inline]) -> String {
+    fn render_inlines(&self, inlines: &[Inline]) -> String {
         let mut output = String::new();
 
         for inline in inlines {
-            output.push_str(&Self::render_inline(inline));
+            output.push_str(&self.render_inline(inline));
         }
 
         output
     }
 
-    fn render_inline(inline: &Inline) -> String {
+    fn render_inline(&self, inline: &Inline) -> String {
         match inline {
-            Inline::Text(text) => Self::escape_html(&text),
+            Inline::Text(text) => Self::escape_html(text),
             Inline::Strong(children) => {
-                let content = Self::render_inlines(children);
+                let content = self.render_inlines(children);
                 format!("<strong>{content}</strong>")
             }
             Inline::Emphasis(children) => {
-                let content = Self::render_inlines(children);
+                let content = self.render_inlines(children);
                 format!("<em>{content}</em>")
             }
-            Inline::Link { children, url } => {
-                let content = Self::render_inlines(children);
-                let safe_url = Self::escape_html(url);
-                format!("<a href=\"{}\">{}</a>", safe_url, content)
+            Inline::Link {
+                children,
+                url,
+                title,
+            } => {
+                let content = self.render_inlines(children);
+                let safe_url = Self::escape_html(&Self::encode_url(url));
+                let title_attr = Self::render_title(title);
+                format!("<a href=\"{}\"{}>{}</a>", safe_url, title_attr, content)
             }
-            Inline::Image { alt, url } => {
+            Inline::Image { alt, url, title } => {
                 let safe_alt = Self::escape_html(alt);
-                let safe_url = Self::escape_html(url);
-                format!("<img src=\"{}\" alt=\"{}\" />", safe_url, safe_alt)
+                let safe_url = Self::escape_html(&Self::encode_url(url));
+                let title_attr = Self::render_title(title);
+                format!(
+                    "<img src=\"{}\" alt=\"{}\"{} />",
+                    safe_url, safe_alt, title_attr
+                )
             }
             Inline::CodeSpan(code) => {
                 let safe_code = Self::escape_html(code);
                 format!("<code>{}</code>", safe_code)
             }
             Inline::Strikethrough(children) => {
-                let content = Self::render_inlines(children);
+                let content = self.render_inlines(children);
                 format!("<del>{}</del>", content)
             }
             Inline::RawHtml(html) => html.to_string(),
+            Inline::HardBreak => "<br />\n".to_string(),
+            Inline::FootnoteReference {
+                label,
+                number,
+                index,
+            } => {
+                let id = Self::escape_html(label);
+                let href = Self::escape_html(&Self::encode_url(label));
+                let suffix = if *index == 1 {
+                    String::new()
+                } else {
+                    format!("-{index}")
+                };
+                format!(
+                    "<sup class=\"footnote-ref\"><a href=\"#fn-{href}\" id=\"fnref-{id}{suffix}\" data-footnote-ref>{number}</a></sup>"
+                )
+            }
+            Inline::SoftBreak => match self.options.soft_break {
+                SoftBreakStyle::Newline => "\n".to_string(),
+                SoftBreakStyle::Space => " ".to_string(),
+                SoftBreakStyle::HardBreak => "<br />\n".to_string(),
+            },
+        }
+    }
+
+    fn render_title(title: &Option<std::borrow::Cow<str>>) -> String {
+        match title {
+            Some(title) => format!(" title=\"{}\"", Self::escape_html(title)),
+            None => String::new(),
         }
     }
 
-    fn escape_html(s: &str) -> String {
+    // 百分号编码 URL 中的空格、非 ASCII 等字符，已有的 %XX 保持不变
+    fn encode_url(url: &str) -> String {
+        let mut output = String::with_capacity(url.len());
+
+        for b in url.bytes() {
+            if b.is_ascii_alphanumeric() || b"-_.!~*'();/?:@&=+$,%#".contains(&b) {
+                output.push(b as char);
+            } else {
+                output.push_str(&format!("%{:02X}", b));
+            }
+        }
+
+        output
+    }
+
+    pub fn escape_html(s: &str) -> String {
         s.replace('&', "&amp;")
             .replace('<', "&lt;")
             .replace('>', "&gt;")
@@ -0,0 +1,111 @@
+use crate::{
+    ast::{Block, ListItem},
+    lexer::{InlineParser, ParseOptions, Parser},
+};
+
+/// 文档中的一个任务列表项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub checked: bool,
+    /// 第一个段落的纯文本
+    pub text: String,
+    /// `[ ]` 中 `[` 在原文中的字节位置
+    pub offset: usize,
+}
+
+impl Task {
+    /// 返回切换勾选状态后的原文，只改动方括号内的一个字节
+    pub fn toggle(&self, source: &str) -> String {
+        let mark = if self.checked { " " } else { "x" };
+        let pos = self.offset + 1;
+
+        format!("{}{}{}", &source[..pos], mark, &source[pos + 1..])
+    }
+}
+
+/// 按出现顺序列出原文中的任务列表项
+pub fn find_tasks(source: &str, options: &ParseOptions) -> Vec<Task> {
+    // 编号时脚注定义会被移到文档末尾，打乱列表项与标记位置的对应，这里不编号
+    let input = Parser::preprocess(source);
+    let mut parser = Parser::with_options(&input, *options);
+    let blocks = parser.parse_unnumbered();
+
+    let mut items = Vec::new();
+    collect_task_items(&blocks, &mut items);
+
+    // 标记的出现顺序与列表项的先序遍历顺序一致
+    let offsets = source_offsets(source, &parser.task_offsets);
+    items
+        .into_iter()
+        .zip(offsets)
+        .map(|(item, offset)| Task {
+            checked: item.checked.unwrap_or(false),
+            text: task_text(item),
+            offset,
+        })
+        .collect()
+}
+
+/// 切换第 `index` 个任务（从 0 开始），返回修改后的原文；任务不存在时返回 `None`
+pub fn toggle_task(source: &str, options: &ParseOptions, index: usize) -> Option<String> {
+    let task = find_tasks(source, options).into_iter().nth(index)?;
+    Some(task.toggle(source))
+}
+
+/// 切换第一个文本为 `text` 的任务，比较时忽略首尾空白
+pub fn toggle_task_by_text(source: &str, options: &ParseOptions, text: &str) -> Option<String> {
+    let task = find_tasks(source, options)
+        .into_iter()
+        .find(|task| task.text.trim() == text.trim())?;
+    Some(task.toggle(source))
+}
+
+fn collect_task_items<'b, 'a>(blocks: &'b [Block<'a>], items: &mut Vec<&'b ListItem<'a>>) {
+    for block in blocks {
+        match block {
+            Block::BlockQuote(children) | Block::FootnoteDefinition { children, .. } => {
+                collect_task_items(children, items)
+            }
+            Block::List {
+                items: list_items, ..
+            } => {
+                for item in list_items {
+                    if item.checked.is_some() {
+                        items.push(item);
+                    }
+                    collect_task_items(&item.children, items);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn task_text(item: &ListItem) -> String {
+    match item.children.first() {
+        Some(Block::Paragraph { children }) => InlineParser::plain_text(children),
+        _ => String::new(),
+    }
+}
+
+// 预处理把 \r\n 换成 \n、制表符换成 4 个空格，按同样的规则把位置换算回原文
+// offsets 按升序排列
+fn source_offsets(source: &str, offsets: &[usize]) -> Vec<usize> {
+    let bytes = source.as_bytes();
+    let mut result = Vec::with_capacity(offsets.len());
+    let (mut i, mut pos) = (0, 0);
+
+    for &offset in offsets {
+        while pos < offset && i < bytes.len() {
+            pos += match bytes[i] {
+                b'\t' => 4,
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => 0,
+                _ => 1,
+            };
+            i += 1;
+        }
+        result.push(i);
+    }
+
+    result
+}